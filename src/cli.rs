@@ -37,6 +37,24 @@ pub struct Args {
     #[arg(short, long, help = "Path to template")]
     pub template: Option<PathBuf>,
 
+    #[arg(
+        long,
+        help = "Directory of icons, searched before the built-in ones (repeatable)"
+    )]
+    pub icon_dir: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Directory resources may be read from, after the directory of the template (repeatable)"
+    )]
+    pub resource_root: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Allow resource paths that are absolute or go up out of the resource directories"
+    )]
+    pub allow_any_resource_path: bool,
+
     #[arg(short, long, default_value = "3000", help = "Port for http server")]
     pub port: u16,
 
@@ -1,13 +1,53 @@
 use axum::response::{IntoResponse, Response};
 use gtmpl::TemplateError;
 use png::DecodingError;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
 use yaml_merge_keys::{serde_yaml, MergeKeyError};
 
 #[derive(Debug)]
 #[allow(dead_code)]
 pub enum DrawingError {
     ImageError(String, DecodingError),
+    /// Path of an image that could not be decoded, and why
+    InvalidImage(String, String),
     ResourceError(String, std::io::Error),
+    /// Name of an icon found neither in the icon directories nor built in
+    UnknownIcon(String),
+    /// Path of a resource, and the files tried for it
+    MissingResource(String, Vec<PathBuf>),
+    /// Path of a resource outside of the resource directories
+    ForbiddenResource(String),
+}
+
+impl Display for DrawingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawingError::ImageError(path, e) => write!(f, "unable to decode {}: {}", path, e),
+            DrawingError::InvalidImage(path, reason) => {
+                write!(f, "invalid image {}: {}", path, reason)
+            }
+            DrawingError::ResourceError(path, e) => write!(f, "unable to read {}: {}", path, e),
+            DrawingError::UnknownIcon(name) => write!(f, "unknown icon {:?}", name),
+            DrawingError::MissingResource(path, searched) => write!(
+                f,
+                "resource {} not found, tried {}",
+                path,
+                searched
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            DrawingError::ForbiddenResource(path) => {
+                write!(
+                    f,
+                    "resource {} is outside of the resource directories",
+                    path
+                )
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -29,7 +69,7 @@ impl IntoResponse for Error {
             Error::MergeKeyError(e) => format!("Merge key error: {e}"),
             Error::InvalidPrimitive(i, e) => format!("Invalid primitive at index {}: {}", i, e),
             Error::HWError(r) => format!("Hardware error: {r}"),
-            Error::DrawingError(_) => "Drawing error".to_string(),
+            Error::DrawingError(e) => format!("Drawing error: {e}"),
         };
 
         println!("Error: {r}");
@@ -124,6 +124,7 @@ async fn run_server(sender: Sender<RefreshSignal>, port: u16) {
     let app = state::route(app);
     let app = templater::route(app);
     let app = debug::route(app);
+    let app = renderer::image_cache::route(app);
 
     let mut sigint = signal(SignalKind::terminate()).unwrap();
     select! {
@@ -204,6 +205,16 @@ async fn main() {
         process::exit(1);
     }));
 
+    renderer::icon::set_icon_dirs(args.icon_dir.clone());
+    renderer::resources::set_resources(renderer::resources::Resources {
+        template_dir: args
+            .template
+            .as_ref()
+            .and_then(|template| template.parent())
+            .map(|dir| dir.to_path_buf()),
+        roots: args.resource_root.clone(),
+        allow_any_path: args.allow_any_resource_path,
+    });
     load_default_template(&args).await;
 
     init_state(&args.json);
@@ -1,30 +1,65 @@
 mod alignment;
+mod barcode;
+mod bars;
+mod baseline;
+mod chart;
+mod clock;
 pub mod container;
+mod decode;
 mod drawing_error;
+mod fits;
+mod fits_file;
+mod font;
+mod gauge;
+mod grid;
+pub mod icon;
 mod image;
+pub mod image_cache;
+mod inverted;
+mod layout;
 mod positioning;
 mod progress;
 mod qrcode;
+mod raster;
+pub mod resources;
+mod richtext;
+mod series;
+mod style;
+mod svg;
+mod table;
 mod text;
 
 use std::fmt::Debug;
 
 use crate::binary_framebuffer::{BinarisedColor, BinaryFrameBuffer};
 use crate::error::{DrawingError, Error};
+use barcode::{draw_barcode, Barcode};
+use bars::{draw_bars, Bars};
+use chart::{draw_chart, Chart};
+use clock::{draw_clock, Clock};
 use container::{draw_container, Container, ShiftedDisplay};
 use drawing_error::IntoDrawingError;
 use embedded_graphics::primitives::Rectangle;
 use embedded_graphics::{pixelcolor::BinaryColor, prelude::*};
+use fits::{draw_fits, Fits};
+use gauge::{draw_gauge, Gauge};
+use icon::{draw_icon, Icon};
 use image::{draw_image, Image};
+use positioning::{resolve_relative, Coordinate, PointSpec, SizeSpec};
 use progress::Progress;
 use qrcode::{draw_qrcode, QRCode};
+use richtext::{draw_rich_text, RichText};
 use serde::{Deserialize, Serialize};
+use style::Style;
+use table::{draw_table, Table};
 use text::{draw_text, TextItem};
 use yaml_merge_keys::serde_yaml;
 
 pub trait ColorFromTemplate {
     fn resolve(color: &Option<String>) -> Self;
     fn invert(&self) -> Self;
+    /// Whether the panel only has two colors, so that shades have to be hatched
+    fn is_binary() -> bool;
 }
 
 // EPD specific implem.
@@ -45,12 +80,38 @@ impl ColorFromTemplate for BinaryColor {
             BinaryColor::On => BinaryColor::Off,
         }
     }
+
+    fn is_binary() -> bool {
+        true
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+/// (background, foreground) colors from optional color/background settings.
+/// When only one is given, the other is its inverse
+pub fn resolve_colors<C: ColorFromTemplate>(
+    color: &Option<String>,
+    background: &Option<String>,
+) -> (C, C) {
+    if color.is_some() && background.is_some() {
+        (C::resolve(background), C::resolve(color))
+    } else if background.is_some() {
+        let back = C::resolve(background);
+        let color = back.invert();
+        (back, color)
+    } else {
+        let color = C::resolve(color);
+        let back = color.invert();
+        (back, color)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(from = "PointSpec", into = "PointSpec")]
 pub struct Point {
     pub x: i32,
     pub y: i32,
+    /// Coordinates relative to the parent, replaced by pixels before drawing
+    pub relative: Option<[Coordinate; 2]>,
 }
 
 impl Into<embedded_graphics::geometry::Point> for Point {
@@ -59,10 +120,13 @@ impl Into<embedded_graphics::geometry::Point> for Point {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(from = "SizeSpec", into = "SizeSpec")]
 pub struct Size {
     pub width: i32,
     pub height: i32,
+    /// Dimensions relative to the parent, replaced by pixels before drawing
+    pub relative: Option<[Coordinate; 2]>,
 }
 
 impl Into<embedded_graphics::geometry::Size> for Size {
@@ -83,9 +147,19 @@ pub enum Primitive {
     #[serde(rename = "x-ignore")]
     Dummy(Dummy),
     Text(TextItem),
+    #[serde(rename = "richtext")]
+    RichText(RichText),
     QRCode(QRCode),
+    Barcode(Barcode),
     Image(Image),
+    Fits(Fits),
+    Icon(Icon),
     Progress(Progress),
+    Chart(Chart),
+    Bars(Bars),
+    Gauge(Gauge),
+    Clock(Clock),
+    Table(Table),
     Container(Container),
 }
 
@@ -93,7 +167,25 @@ pub enum Primitive {
 #[serde(transparent)]
 pub struct PrimitiveWrapper(#[serde(with = "serde_yaml::with::singleton_map")] pub Primitive);
 
+/// Parse a list of primitives, or a mapping with a style block and the list as content
 pub fn parse(yaml: serde_yaml::Value) -> Result<Vec<Primitive>, Error> {
+    // A styled document is drawn as a container covering the display
+    if let Some(mapping) = yaml.as_mapping() {
+        let style = match mapping.get("style") {
+            Some(style) => Some(
+                serde_yaml::from_value::<Style>(style.clone())
+                    .map_err(|e| Error::InvalidPrimitive(0, e))?,
+            ),
+            None => None,
+        };
+        let content = parse(mapping.get("content").cloned().unwrap_or_default())?;
+        return Ok(vec![Primitive::Container(Container {
+            style,
+            content: Some(content),
+            ..Default::default()
+        })]);
+    }
+
     let mut primitives = Vec::new();
 
     let mut id = 1;
@@ -114,7 +206,7 @@ pub fn parse(yaml: serde_yaml::Value) -> Result<Vec<Primitive>, Error> {
 
 pub fn draw<D, TargetColor>(
     display: &mut ShiftedDisplay<D, TargetColor, D::Error>,
-    primitives: &Vec<Primitive>,
+    primitives: &[Primitive],
 ) -> Result<(), DrawingError>
 where
     D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
@@ -122,17 +214,31 @@ where
 {
     let mut result: Result<(), _> = Ok(());
     for primitive in primitives {
+        let mut primitive = resolve_relative(primitive, &display.bounding_box());
+        if display.style().affects(&primitive) {
+            display.style().apply(primitive.to_mut());
+        }
+        let primitive = &*primitive;
         println!("Rendering {:?}", primitive);
         let problem = match primitive {
             Primitive::Dummy(_) => Ok(()),
             Primitive::Text(text) => draw_text(display, text),
+            Primitive::RichText(rich) => draw_rich_text(display, rich),
             Primitive::Image(image) => draw_image(display, image),
+            Primitive::Fits(fits) => draw_fits(display, fits),
+            Primitive::Icon(icon) => draw_icon(display, icon),
             Primitive::QRCode(qr) => draw_qrcode(display, qr),
+            Primitive::Barcode(barcode) => draw_barcode(display, barcode),
             Primitive::Progress(progress) => progress::draw_progress(display, progress),
+            Primitive::Chart(chart) => draw_chart(display, chart),
+            Primitive::Bars(bars) => draw_bars(display, bars),
+            Primitive::Gauge(gauge) => draw_gauge(display, gauge),
+            Primitive::Clock(clock) => draw_clock(display, clock),
+            Primitive::Table(table) => draw_table(display, table),
             Primitive::Container(container) => draw_container(display, container),
         };
         if let Err(err) = problem {
-            println!("Error rendering {:?}: {:?}", primitive, &err);
+            println!("Error rendering {:?}: {}", primitive, &err);
             if result.is_ok() {
                 result = Err(err);
             }
@@ -231,10 +337,15 @@ mod tests {
             result[1],
             Primitive::Text(TextItem {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 0, y: 0 },
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
                 align: None,
                 font: None,
-                color: None
+                color: None,
+                ..Default::default()
             })
         );
     }
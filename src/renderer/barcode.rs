@@ -0,0 +1,323 @@
+use crate::error::DrawingError;
+
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Alignment;
+
+use super::drawing_error::IntoDrawingError;
+use super::font::FontChain;
+use super::grid::GridCell;
+use super::inverted::Inverted;
+use super::positioning::Length;
+use super::text::{draw_text, TextItem};
+use super::{resolve_colors, ColorFromTemplate, Point};
+use embedded_graphics::prelude::*;
+
+use rxing::{BarcodeFormat, EncodeHints, MultiFormatWriter, Writer};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    Code128,
+    Ean13,
+    Code39,
+    DataMatrix,
+    Aztec,
+}
+
+impl Symbology {
+    fn format(&self) -> BarcodeFormat {
+        match self {
+            Symbology::Code128 => BarcodeFormat::CODE_128,
+            Symbology::Ean13 => BarcodeFormat::EAN_13,
+            Symbology::Code39 => BarcodeFormat::CODE_39,
+            Symbology::DataMatrix => BarcodeFormat::DATA_MATRIX,
+            Symbology::Aztec => BarcodeFormat::AZTEC,
+        }
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Barcode {
+    pub value: String,
+    pub format: Symbology,
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub color: Option<String>,
+    pub background: Option<String>,
+    pub width: Length,
+    pub height: Length,
+    /// Quiet zone, in modules. Defaults to the symbology recommendation
+    pub margin: Option<u32>,
+    /// Print the value below the code
+    pub caption: Option<bool>,
+    pub caption_font: Option<String>,
+}
+
+pub fn draw_barcode<D, TargetColor>(display: &mut D, barcode: &Barcode) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let (back, front) = resolve_colors::<TargetColor>(&barcode.color, &barcode.background);
+
+    display
+        .fill_solid(
+            &Rectangle {
+                top_left: barcode.position.clone().into(),
+                size: embedded_graphics::prelude::Size::new(
+                    barcode.width.pixels(),
+                    barcode.height.pixels(),
+                ),
+            },
+            back,
+        )
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    let caption_height = if barcode.caption.unwrap_or(false) {
+        FontChain::parse(&barcode.caption_font).line_height()
+    } else {
+        0
+    };
+    let code_height = barcode.height.pixels().saturating_sub(caption_height);
+
+    let hints = EncodeHints {
+        Margin: barcode.margin.map(|m| m.to_string()),
+        ..Default::default()
+    };
+    let matrix = MultiFormatWriter.encode_with_hints(
+        &barcode.value,
+        &barcode.format.format(),
+        barcode.width.pixels() as i32,
+        code_height as i32,
+        &hints,
+    );
+    if matrix.is_err() {
+        println!("Error rendering barcode : {:?}", matrix.err());
+        return Ok(());
+    }
+    let matrix = matrix.unwrap();
+
+    // Center the symbol in the available space
+    let shift = embedded_graphics::prelude::Point {
+        x: barcode.position.x + (barcode.width.pixels().saturating_sub(matrix.width()) / 2) as i32,
+        y: barcode.position.y + (code_height.saturating_sub(matrix.height()) / 2) as i32,
+    };
+
+    display
+        .draw_iter(
+            (0..matrix.height())
+                .flat_map(|y| (0..matrix.width()).map(move |x| (x, y)))
+                .filter(|(x, y)| matrix.get(*x, *y))
+                .map(|(x, y)| {
+                    embedded_graphics::Pixel(
+                        shift + embedded_graphics::prelude::Point::new(x as i32, y as i32),
+                        front,
+                    )
+                }),
+        )
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    if caption_height > 0 {
+        let caption = TextItem {
+            value: barcode.value.clone(),
+            position: Point {
+                x: barcode.position.x,
+                y: barcode.position.y + code_height as i32,
+                ..Default::default()
+            },
+            font: barcode.caption_font.clone(),
+            color: barcode.color.clone(),
+            align: Some(Alignment::Center),
+            width: Some(barcode.width),
+            height: Some(caption_height.into()),
+            wrap: Some(false),
+            ..Default::default()
+        };
+        // Like the bars, a caption with only a background takes the opposite color
+        match (&barcode.color, &barcode.background) {
+            (None, Some(_)) => draw_text(
+                &mut Inverted(display),
+                &TextItem {
+                    color: barcode.background.clone(),
+                    ..caption
+                },
+            )?,
+            _ => draw_text(display, &caption)?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+
+    #[test]
+    fn test_code128() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 64,
+                height: 20,
+            },
+            vec![Primitive::Barcode(Barcode {
+                value: "EQ-042".to_string(),
+                format: Symbology::Code128,
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                cell: None,
+                color: Some("0".to_string()),
+                background: None,
+                width: 64.into(),
+                height: 20.into(),
+                margin: Some(2),
+                caption: Some(true),
+                caption_font: Some("4x6".to_string()),
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+█  █ ██ ████ ███  █ ███  █ ███   █ ██  █   ██ ██   █  ██  ██ ██ 
+████████████████████ ▄▄█▀▄▀█████▀▄▀█ █ █▀▄▀█████████████████████
+████████████████████ ▄██ █ █▄▄▄█ ▄ █▄▄ ██▀▄█████████████████████
+████████████████████▄▄▄██▄▀██████▄████▄█▄▄▄█████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_caption_background() {
+        // With only a background, the caption takes the color of the bars
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 64,
+                height: 12,
+            },
+            vec![Primitive::Barcode(Barcode {
+                value: "EQ-042".to_string(),
+                format: Symbology::Code128,
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                cell: None,
+                color: None,
+                background: Some("0".to_string()),
+                width: 64.into(),
+                height: 12.into(),
+                margin: Some(2),
+                caption: Some(true),
+                caption_font: Some("4x6".to_string()),
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+ ██ █  █    █   ██ █   ██ █   ███ █  ██ ███  █  ███ ██  ██  █  █
+ ██ █  █    █   ██ █   ██ █   ███ █  ██ ███  █  ███ ██  ██  █  █
+ ██ █  █    █   ██ █   ██ █   ███ █  ██ ███  █  ███ ██  ██  █  █
+                    █▀▀ ▄▀▄     ▄▀▄ █ █ ▄▀▄                     
+                    █▀  █ █ ▀▀▀ █▀█ ▀▀█  ▄▀                     
+                    ▀▀▀  ▀▄      ▀    ▀ ▀▀▀                     
+"#
+        );
+    }
+
+    #[test]
+    fn test_datamatrix() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 16,
+            },
+            vec![Primitive::Barcode(Barcode {
+                value: "EQ-042".to_string(),
+                format: Symbology::DataMatrix,
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                cell: None,
+                color: Some("0".to_string()),
+                background: None,
+                width: 32.into(),
+                height: 16.into(),
+                margin: None,
+                caption: None,
+                caption_font: None,
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████████████████████
+██████████ █ ▀ █▄█▄█ ▀██████████
+██████████ █ █▀▀▄▄█▀▀▀██████████
+██████████ ████ ▄▀▀ █▀██████████
+██████████ ▀▄▄ ▄▄▄▀ ▄▀██████████
+██████████ ██▄ ▄▀▀█▀█▀██████████
+██████████  ▀  ▀  ▀▀ ▀██████████
+████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 16,
+                height: 4,
+            },
+            vec![Primitive::Barcode(Barcode {
+                value: "not a number".to_string(),
+                format: Symbology::Ean13,
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                cell: None,
+                color: Some("0".to_string()),
+                background: None,
+                width: 16.into(),
+                height: 4.into(),
+                margin: None,
+                caption: None,
+                caption_font: None,
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████
+████████████████
+"#
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::error::DrawingError;
+
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Alignment;
+
+use super::drawing_error::IntoDrawingError;
+use super::font::FontChain;
+use super::grid::GridCell;
+use super::positioning::{
+    place_rectangle, Direction, HorizontalAlignment, Length, VerticalAlignment,
+};
+use super::series::{bounds, format_value, resolve_series};
+use super::text::{draw_text, TextItem};
+use super::{ColorFromTemplate, Point};
+use embedded_graphics::prelude::{DrawTarget, PixelColor};
+use serde::{Deserialize, Serialize};
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Bars {
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub width: Length,
+    pub height: Length,
+
+    pub align: Option<HorizontalAlignment>,
+    pub vertical_align: Option<VerticalAlignment>,
+
+    /// Vertical (default): bars grow upward. Horizontal: bars grow to the right
+    pub direction: Option<Direction>,
+
+    pub values: Option<Vec<f64>>,
+    /// Dotted path to an array in the state (used when values is not set)
+    pub path: Option<String>,
+
+    /// Scale. min defaults to 0 (1 for log scale), max to the largest value
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub log: Option<bool>,
+
+    /// Pixels between bars. Default to 1
+    pub spacing: Option<u32>,
+
+    /// Print each value at the end of its bar
+    pub labels: Option<bool>,
+    pub font: Option<String>,
+
+    pub color: Option<String>,
+}
+
+fn scale(v: f64, log: bool) -> f64 {
+    if log {
+        if v > 0.0 {
+            v.log10()
+        } else {
+            f64::NEG_INFINITY
+        }
+    } else {
+        v
+    }
+}
+
+pub fn draw_bars<D, TargetColor>(display: &mut D, bars: &Bars) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let color = TargetColor::resolve(&bars.color);
+    let log = bars.log.unwrap_or(false);
+    let values = resolve_series(&bars.values, &bars.path);
+    if values.is_empty() {
+        return Ok(());
+    }
+
+    let scaled: Vec<f64> = values.iter().map(|v| scale(*v, log)).collect();
+    let (min, max) = bounds(
+        &scaled,
+        Some(scale(bars.min.unwrap_or(if log { 1.0 } else { 0.0 }), log)),
+        bars.max.map(|m| scale(m, log)),
+    );
+
+    let origin = place_rectangle(
+        embedded_graphics::geometry::Size {
+            width: bars.width.pixels(),
+            height: bars.height.pixels(),
+        },
+        bars.align,
+        bars.vertical_align,
+        bars.position.clone(),
+    );
+    let horizontal = matches!(bars.direction, Some(Direction::Horizontal));
+
+    // Across: the dimension shared by all the bars. Along: the dimension of the bar length
+    let (across, along) = if horizontal {
+        (bars.height.pixels(), bars.width.pixels())
+    } else {
+        (bars.width.pixels(), bars.height.pixels())
+    };
+
+    let chain = FontChain::parse(&bars.font);
+    let labels = bars.labels.unwrap_or(false);
+    // Room kept for the labels at the end of the bars
+    let label_room = match (labels, horizontal) {
+        (false, _) => 0,
+        (true, false) => chain.line_height(),
+        (true, true) => values
+            .iter()
+            .map(|v| chain.line_width(&format_value(*v)) + 1)
+            .max()
+            .unwrap_or(0),
+    };
+    let along = along.saturating_sub(label_room);
+
+    let count = values.len() as u32;
+    let spacing = bars.spacing.unwrap_or(1);
+    let total_spacing = spacing * (count - 1);
+    if total_spacing >= across {
+        println!("Not enough room for {} bars", count);
+        return Ok(());
+    }
+    let available = across - total_spacing;
+
+    for (i, (value, scaled)) in values.iter().zip(scaled.iter()).enumerate() {
+        let i = i as u32;
+        // Distribute the remaining pixels evenly
+        let start = i * available / count + i * spacing;
+        let thickness = (i + 1) * available / count - i * available / count;
+
+        let ratio = if scaled.is_finite() {
+            ((scaled - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let length = (ratio * along as f64).round() as u32;
+
+        let rect = if horizontal {
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(origin.x, origin.y + start as i32),
+                embedded_graphics::prelude::Size::new(length, thickness),
+            )
+        } else {
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(
+                    origin.x + start as i32,
+                    origin.y + (bars.height.pixels() - length) as i32,
+                ),
+                embedded_graphics::prelude::Size::new(thickness, length),
+            )
+        };
+        display
+            .fill_solid(&rect, color)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+
+        if labels && value.is_finite() {
+            let position = if horizontal {
+                Point {
+                    x: origin.x + length as i32 + 1,
+                    y: origin.y
+                        + start as i32
+                        + (thickness as i32 - chain.line_height() as i32) / 2,
+                    ..Default::default()
+                }
+            } else {
+                Point {
+                    x: origin.x + start as i32 + thickness as i32 / 2,
+                    y: rect.top_left.y - chain.line_height() as i32,
+                    ..Default::default()
+                }
+            };
+            draw_text(
+                display,
+                &TextItem {
+                    value: format_value(*value),
+                    position,
+                    font: bars.font.clone(),
+                    color: bars.color.clone(),
+                    align: Some(if horizontal {
+                        Alignment::Left
+                    } else {
+                        Alignment::Center
+                    }),
+                    ..Default::default()
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+
+    #[test]
+    fn test_vertical() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 16,
+            },
+            vec![Primitive::Bars(Bars {
+                position: Point {
+                    x: 1,
+                    y: 0,
+                    ..Default::default()
+                },
+                width: 30.into(),
+                height: 16.into(),
+                values: Some(vec![1.0, 4.0, 2.0]),
+                spacing: Some(2),
+                labels: Some(true),
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+██████████████ █ ███████████████
+██████████████▄▄ ███████████████
+████████████████▄█████████▀█████
+█████▀█████         █████▄█ ████
+████▄ █████         █████▀ ▀████
+████▀ ▀████         ██▀▀▀▀▀▀▀▀▀█
+█▀▀▀▀▀▀▀▀██         ██         █
+█        ██         ██         █
+"#
+        );
+    }
+
+    #[test]
+    fn test_horizontal_log() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 12,
+            },
+            vec![Primitive::Bars(Bars {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                width: 32.into(),
+                height: 12.into(),
+                direction: Some(Direction::Horizontal),
+                values: Some(vec![10.0, 1000.0, 0.0, 100.0]),
+                log: Some(true),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+           █████████████████████
+▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀
+▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄
+████████████████████████████████
+▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀███████████
+                     ███████████
+"#
+        );
+    }
+}
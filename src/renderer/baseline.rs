@@ -0,0 +1,44 @@
+use embedded_graphics::text::Baseline;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use Baseline::*;
+
+pub fn default() -> Option<Baseline> {
+    None
+}
+
+pub fn serialize<S>(v: &Option<Baseline>, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if v.is_none() {
+        return Option::<String>::None.serialize(s);
+    }
+
+    let v: &str = match v.unwrap() {
+        Top => "top",
+        Bottom => "bottom",
+        Middle => "middle",
+        Alphabetic => "alphabetic",
+    };
+
+    v.to_string().serialize(s)
+}
+
+pub fn deserialize<'de, D>(d: D) -> Result<Option<Baseline>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(d)?.map(|e| e.to_lowercase()) {
+        None => Ok(None),
+        Some(v) => match v.as_str() {
+            "top" => Ok(Some(Baseline::Top)),
+            "bottom" => Ok(Some(Baseline::Bottom)),
+            "middle" => Ok(Some(Baseline::Middle)),
+            "alphabetic" => Ok(Some(Baseline::Alphabetic)),
+            v => {
+                println!("Invalid baseline : {:?}", v);
+                Ok(None)
+            }
+        },
+    }
+}
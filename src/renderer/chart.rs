@@ -0,0 +1,291 @@
+use crate::error::DrawingError;
+
+use embedded_graphics::primitives::Line;
+use embedded_graphics::text::Alignment;
+
+use super::drawing_error::IntoDrawingError;
+use super::font::FontChain;
+use super::grid::GridCell;
+use super::positioning::Length;
+use super::series::{bounds, format_value, resolve_series};
+use super::text::{draw_text, TextItem};
+use super::{ColorFromTemplate, Point};
+use embedded_graphics::prelude::{DrawTarget, PixelColor, PointsIter};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PlotMode {
+    Line,
+    Step,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Chart {
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub width: Length,
+    pub height: Length,
+
+    pub values: Option<Vec<f64>>,
+    /// Dotted path to an array in the state (used when values is not set)
+    pub path: Option<String>,
+
+    /// Line (default) or step plot
+    pub mode: Option<PlotMode>,
+
+    /// Fixed y bounds. Default to the range of the values
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+
+    /// Draw the left and bottom axes
+    pub axes: Option<bool>,
+    /// Number of tick marks on the axes
+    pub x_ticks: Option<u32>,
+    pub y_ticks: Option<u32>,
+    /// Print the y bounds left of the plot
+    pub labels: Option<bool>,
+    pub font: Option<String>,
+
+    /// Value of an horizontal dashed line
+    pub threshold: Option<f64>,
+
+    pub color: Option<String>,
+}
+
+// Evenly spread count positions in [start, start + len - 1]
+fn spread(start: i32, len: i32, count: u32) -> Vec<i32> {
+    match count {
+        0 => vec![],
+        1 => vec![start],
+        _ => (0..count as i32)
+            .map(|i| start + (i * (len - 1)) / (count as i32 - 1))
+            .collect(),
+    }
+}
+
+pub fn draw_chart<D, TargetColor>(display: &mut D, chart: &Chart) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let color = TargetColor::resolve(&chart.color);
+    let values = resolve_series(&chart.values, &chart.path);
+    let (min, max) = bounds(&values, chart.min, chart.max);
+    let axes = chart.axes.unwrap_or(false);
+
+    let mut left = chart.position.x;
+    if chart.labels.unwrap_or(false) {
+        let chain = FontChain::parse(&chart.font);
+        let label_width = std::cmp::max(
+            chain.line_width(&format_value(min)),
+            chain.line_width(&format_value(max)),
+        ) + 1;
+        for (value, y, valign) in [
+            (max, chart.position.y, 0),
+            (min, chart.position.y + chart.height.pixels() as i32, 1),
+        ] {
+            draw_text(
+                display,
+                &TextItem {
+                    value: format_value(value),
+                    position: Point {
+                        x: left + label_width as i32 - 2,
+                        y: y - valign * chain.line_height() as i32,
+                        ..Default::default()
+                    },
+                    font: chart.font.clone(),
+                    color: chart.color.clone(),
+                    align: Some(Alignment::Right),
+                    ..Default::default()
+                },
+            )?;
+        }
+        left += label_width as i32;
+    }
+
+    let mut pixels = Vec::new();
+    let bottom = chart.position.y + chart.height.pixels() as i32 - 1;
+    let right = chart.position.x + chart.width.pixels() as i32 - 1;
+    if axes {
+        let corner = embedded_graphics::prelude::Point::new(left, bottom);
+        pixels.extend(
+            Line::new(
+                embedded_graphics::prelude::Point::new(left, chart.position.y),
+                corner,
+            )
+            .points(),
+        );
+        pixels.extend(
+            Line::new(
+                corner,
+                embedded_graphics::prelude::Point::new(right, bottom),
+            )
+            .points(),
+        );
+        left += 1;
+    }
+
+    let top = chart.position.y;
+    let plot_width = right - left + 1;
+    let plot_height = bottom - top + if axes { 0 } else { 1 };
+    if plot_width <= 0 || plot_height <= 0 {
+        return Ok(());
+    }
+
+    if axes {
+        for y in spread(top, plot_height, chart.y_ticks.unwrap_or(0)) {
+            pixels.push(embedded_graphics::prelude::Point::new(left, y));
+            pixels.push(embedded_graphics::prelude::Point::new(left + 1, y));
+        }
+        for x in spread(left, plot_width, chart.x_ticks.unwrap_or(0)) {
+            pixels.push(embedded_graphics::prelude::Point::new(x, bottom - 1));
+            pixels.push(embedded_graphics::prelude::Point::new(x, bottom - 2));
+        }
+    }
+
+    let y_of = |v: f64| -> i32 {
+        let ratio = ((max - v) / (max - min)).clamp(0.0, 1.0);
+        top + (ratio * (plot_height - 1) as f64).round() as i32
+    };
+
+    if let Some(threshold) = chart.threshold {
+        if threshold >= min && threshold <= max {
+            let y = y_of(threshold);
+            pixels.extend(
+                (left..=right)
+                    .filter(|x| (x - left) % 4 < 2)
+                    .map(|x| embedded_graphics::prelude::Point::new(x, y)),
+            );
+        }
+    }
+
+    let xs = spread(left, plot_width, values.len() as u32);
+    let points: Vec<Option<embedded_graphics::prelude::Point>> = values
+        .iter()
+        .zip(xs.iter())
+        .map(|(v, x)| {
+            if v.is_finite() {
+                Some(embedded_graphics::prelude::Point::new(*x, y_of(*v)))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    for (i, point) in points.iter().enumerate() {
+        let Some(point) = point else { continue };
+        pixels.push(*point);
+        let Some(Some(next)) = points.get(i + 1) else {
+            continue;
+        };
+        match chart.mode.unwrap_or(PlotMode::Line) {
+            PlotMode::Line => pixels.extend(Line::new(*point, *next).points()),
+            PlotMode::Step => {
+                let corner = embedded_graphics::prelude::Point::new(next.x, point.y);
+                pixels.extend(Line::new(*point, corner).points());
+                pixels.extend(Line::new(corner, *next).points());
+            }
+        }
+    }
+
+    display
+        .draw_iter(
+            pixels
+                .into_iter()
+                .map(|p| embedded_graphics::Pixel(p, color)),
+        )
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 16,
+            },
+            vec![Primitive::Chart(Chart {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                width: 32.into(),
+                height: 16.into(),
+                values: Some(vec![1.0, 3.0, 2.0, f64::NAN, 0.5, 0.0, 4.0]),
+                min: Some(0.0),
+                axes: Some(true),
+                y_ticks: Some(3),
+                threshold: Some(2.5),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+ ▄▄████████████████████████████ 
+ █████████████████████████████ █
+ ▀▀██▀ ▀▀▀▀██▀▀██▀▀██▀▀██▀▀██▀ █
+ ▀▀█▀▄███▄▄▀████████████████▀▄██
+ █▀▄████████████████████████ ███
+ ▀▄████████████████████████ ████
+ ████████████████████▄▄▀▀█▀▄████
+   ▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀  ▀▀▀▀▀
+"#
+        );
+    }
+
+    #[test]
+    fn test_step_with_labels() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 16,
+            },
+            vec![Primitive::Chart(Chart {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                width: 32.into(),
+                height: 16.into(),
+                values: Some(vec![1.0, 3.0, 2.0, 2.0, 9.0]),
+                mode: Some(PlotMode::Step),
+                labels: Some(true),
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+▀▄▀████████████████████████████ 
+█▄ ████████████████████████████ 
+▄▄█████████████████████████████ 
+███████████████████████████████ 
+███████████████████████████████ 
+▀ █████████▀▀▀▀▀▀▀▀████████████ 
+█ █████████ ██████ ▀▀▀▀▀▀▀▀▀▀▀▀ 
+▄▄▄██▀▀▀▀▀▀ ████████████████████
+"#
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use crate::error::DrawingError;
+use crate::state;
+
+use chrono::{Local, Offset, TimeZone, Utc};
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle};
+use embedded_graphics::Drawable;
+
+use super::drawing_error::IntoDrawingError;
+use super::grid::GridCell;
+use super::positioning::{place_rectangle, HorizontalAlignment, Length, VerticalAlignment};
+use super::{ColorFromTemplate, Point};
+use embedded_graphics::prelude::{DrawTarget, PixelColor, Primitive};
+use serde::{Deserialize, Serialize};
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Clock {
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub diameter: Length,
+
+    pub align: Option<HorizontalAlignment>,
+    pub vertical_align: Option<VerticalAlignment>,
+
+    /// Unix timestamp, usually {{ time 60 }} so that the display is refreshed.
+    /// Default to now
+    pub time: Option<f64>,
+    /// Dotted path to a unix timestamp in the state (used when time is not set)
+    pub path: Option<String>,
+
+    /// IANA name (Europe/Paris), UTC offset (+02:00) or local (default)
+    pub timezone: Option<String>,
+
+    /// Draw the hour marks. Default to true
+    pub ticks: Option<bool>,
+    pub seconds: Option<bool>,
+    /// Add a 24h hand showing the local sidereal time at this longitude (degrees east)
+    pub sidereal_longitude: Option<f64>,
+
+    pub color: Option<String>,
+}
+
+/// Seconds to add to UTC for the timezone at the given instant
+fn utc_offset(timezone: &Option<String>, timestamp: i64) -> Option<i32> {
+    let utc = Utc.timestamp_opt(timestamp, 0).single()?;
+    let spec = match timezone.as_deref() {
+        None | Some("local") => {
+            return Some(utc.with_timezone(&Local).offset().fix().local_minus_utc())
+        }
+        Some(spec) => spec.trim(),
+    };
+
+    if let Some(sign) = match spec.chars().next() {
+        Some('+') => Some(1),
+        Some('-') => Some(-1),
+        _ => None,
+    } {
+        let digits: String = spec[1..].chars().filter(|c| *c != ':').collect();
+        // Only ASCII digits, so that the string can be sliced by bytes
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let (hours, minutes) = match digits.len() {
+            1 | 2 => (digits.parse::<i32>().ok()?, 0),
+            4 => (
+                digits[..2].parse::<i32>().ok()?,
+                digits[2..].parse::<i32>().ok()?,
+            ),
+            _ => return None,
+        };
+        return Some(sign * (hours * 3600 + minutes * 60));
+    }
+
+    let tz = chrono_tz::Tz::from_str(spec).ok()?;
+    Some(utc.with_timezone(&tz).offset().fix().local_minus_utc())
+}
+
+/// Local sidereal time, in hours
+fn sidereal_time(timestamp: f64, longitude: f64) -> f64 {
+    let days_since_j2000 = timestamp / 86400.0 + 2440587.5 - 2451545.0;
+    let gmst = 18.697374558 + 24.06570982441908 * days_since_j2000;
+    (gmst + longitude / 15.0).rem_euclid(24.0)
+}
+
+fn timestamp(clock: &Clock) -> f64 {
+    if let Some(time) = clock.time {
+        return time;
+    }
+    if let Some(path) = &clock.path {
+        match state::get_path(path).and_then(|v| v.as_f64()) {
+            Some(time) => return time,
+            None => println!("No timestamp at {}", path),
+        }
+    }
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+pub fn draw_clock<D, TargetColor>(display: &mut D, clock: &Clock) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let color = TargetColor::resolve(&clock.color);
+    let time = timestamp(clock);
+    let offset = utc_offset(&clock.timezone, time.floor() as i64).unwrap_or_else(|| {
+        println!("Invalid timezone {:?}, using UTC", clock.timezone);
+        0
+    });
+    let seconds_of_day = (time + offset as f64).rem_euclid(86400.0);
+
+    let origin = place_rectangle(
+        embedded_graphics::geometry::Size {
+            width: clock.diameter.pixels(),
+            height: clock.diameter.pixels(),
+        },
+        clock.align,
+        clock.vertical_align,
+        clock.position.clone(),
+    );
+    let top_left: embedded_graphics::prelude::Point = origin.into();
+    let face = Circle::new(top_left, clock.diameter.pixels());
+    let center = face.center();
+    let radius = clock.diameter.pixels() as f64 / 2.0;
+
+    // Point at a distance from the center, for a fraction of a turn from 12 o'clock
+    let polar = |turn: f64, distance: f64| {
+        let angle = turn * std::f64::consts::TAU;
+        center
+            + embedded_graphics::prelude::Point::new(
+                (distance * angle.sin()).round() as i32,
+                (-distance * angle.cos()).round() as i32,
+            )
+    };
+
+    face.into_styled(PrimitiveStyle::with_stroke(color, 1))
+        .draw(display)
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    if clock.ticks.unwrap_or(true) {
+        for hour in 0..12 {
+            // Longer marks on the quarters
+            let length = if hour % 3 == 0 { 4.0 } else { 2.0 };
+            let turn = hour as f64 / 12.0;
+            Line::new(
+                polar(turn, radius - 2.0),
+                polar(turn, radius - 1.0 - length),
+            )
+            .into_styled(PrimitiveStyle::with_stroke(color, 1))
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+        }
+    }
+
+    let mut hands = vec![
+        ((seconds_of_day / 43200.0).fract(), 0.5, 2),
+        ((seconds_of_day / 3600.0).fract(), 0.8, 1),
+    ];
+    if clock.seconds.unwrap_or(false) {
+        hands.push(((seconds_of_day / 60.0).fract(), 0.9, 1));
+    }
+    for (turn, length, width) in hands {
+        Line::new(center, polar(turn, radius * length))
+            .into_styled(PrimitiveStyle::with_stroke(color, width))
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+    }
+
+    // The sidereal hand does one turn per sidereal day, and ends with a small circle
+    if let Some(longitude) = clock.sidereal_longitude {
+        let turn = sidereal_time(time, longitude) / 24.0;
+        let tip = polar(turn, radius * 0.7);
+        Line::new(center, polar(turn, radius * 0.7 - 2.0))
+            .into_styled(PrimitiveStyle::with_stroke(color, 1))
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+        Circle::with_center(tip, 5)
+            .into_styled(PrimitiveStyle::with_stroke(color, 1))
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+    }
+
+    Circle::with_center(center, 3)
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(display)
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+
+    #[test]
+    fn test_utc_offset() {
+        // 2024-07-01 12:00:00 UTC
+        let summer = 1719835200;
+        assert_eq!(utc_offset(&Some("UTC".to_string()), summer), Some(0));
+        assert_eq!(utc_offset(&Some("+02:00".to_string()), summer), Some(7200));
+        assert_eq!(utc_offset(&Some("-0530".to_string()), summer), Some(-19800));
+        assert_eq!(utc_offset(&Some("+9".to_string()), summer), Some(32400));
+        assert_eq!(
+            utc_offset(&Some("Europe/Paris".to_string()), summer),
+            Some(7200)
+        );
+        // 2024-01-01 12:00:00 UTC
+        assert_eq!(
+            utc_offset(&Some("Europe/Paris".to_string()), 1704110400),
+            Some(3600)
+        );
+        assert_eq!(utc_offset(&Some("Mars/Olympus".to_string()), summer), None);
+        assert_eq!(utc_offset(&Some("+1é1".to_string()), summer), None);
+    }
+
+    #[test]
+    fn test_sidereal_time() {
+        // J2000.0
+        assert!((sidereal_time(946728000.0, 0.0) - 18.697374558).abs() < 1e-6);
+        assert!((sidereal_time(946728000.0, 30.0) - 20.697374558).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_render() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 32,
+            },
+            vec![Primitive::Clock(Clock {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                diameter: 32.into(),
+                // 2024-07-01 12:00:00 UTC, 15:10:00 at +03:10
+                time: Some(1719835200.0),
+                timezone: Some("+03:10".to_string()),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+█████████▀▀▀▄▄▄ ▄▄▄▄▀▀▀█████████
+██████▀  ██████ ██████▀▄ ▀██████
+████▀▄██▄██████▄█████▄████▄▀████
+██▀ ████████████████████████ ▀██
+█▀▄▄▄█████████████████████ ▄█▄▀█
+█ ████████████████████▀▀▄▄████ █
+ █████████████████▀▀▄▄█████████ 
+ ▀▀▀▀█████████▀     ▀▀▀▀██▀▀▀▀█ 
+ ██████████████▄████▄▄▄▄███████ 
+ ██████████████████████████████ 
+█ ████████████████████████▀███ █
+█▄▀▄▄██████████████████████▄█▀▄█
+██▄ ████████████████████████ ▄██
+████▄▀██ ██████ █████▄▀███▀▄████
+██████▄ ▀██████ ███████▀ ▄██████
+█████████▄▄▄▀▀▀▀▀▀▀▀▄▄▄█████████
+"#
+        );
+    }
+}
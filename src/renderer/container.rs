@@ -1,20 +1,64 @@
 use crate::error::DrawingError;
 
-use super::{drawing_error::IntoDrawingError, ColorFromTemplate, Point, Primitive, Size};
+use super::{
+    drawing_error::IntoDrawingError,
+    grid::{GridCell, Track},
+    layout::{flow, measure, ItemAlignment, Justify, Layout},
+    positioning::resolve_relative,
+    resolve_colors,
+    style::Style,
+    ColorFromTemplate, Point, Primitive, Size,
+};
 use embedded_graphics::{
-    prelude::{Dimensions, DrawTarget, PixelColor},
-    primitives::Rectangle,
-    Pixel,
+    prelude::{Dimensions, DrawTarget, PixelColor, Primitive as _},
+    primitives::{
+        ContainsPoint, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle,
+        StrokeAlignment,
+    },
+    Drawable, Pixel,
 };
 use serde::{Deserialize, Serialize};
 
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Container {
     pub position: Point,
-    pub size: Size,
+    /// Default to the remaining space of the parent, from position
+    pub size: Option<Size>,
     pub rotate: Option<i32>,
 
+    /// Absolute (default): children use their own position.
+    /// Row, column and stack: children are measured and placed one after the other
+    pub layout: Option<Layout>,
+    /// Pixels between children in row, column and grid layouts
+    pub spacing: Option<u32>,
+    pub padding: Option<u32>,
+    /// Placement of the children across the layout direction
+    pub align_items: Option<ItemAlignment>,
+    /// Placement of the children along the layout direction, when none grows
+    pub justify: Option<Justify>,
+    /// Share of the free space given to this container in a row or column parent
+    pub grow: Option<u32>,
+
+    /// Tracks of the grid layout. Default to a single 1fr column, and auto rows as needed
+    pub rows: Option<Vec<Track>>,
+    pub columns: Option<Vec<Track>>,
+    /// Cell of this container in a grid parent. Children without one fill the free cells
+    pub cell: Option<GridCell>,
+
+    /// Fill the container with this color. Inverted containers default to white
+    pub background: Option<String>,
+    /// Width of the border, drawn inside the container
+    pub border: Option<u32>,
+    pub border_color: Option<String>,
+    /// Radius of the rounded corners. The content is clipped to the rounded shape
+    pub radius: Option<u32>,
+    /// Invert everything drawn by the container: background, border and content
+    pub invert: Option<bool>,
+
+    /// Defaults for the content, over the ones inherited from the parent
+    pub style: Option<Style>,
+
     pub content: Option<Vec<Primitive>>,
 }
 
@@ -41,6 +85,10 @@ where
     inner_bounds: Rectangle,
     outer_bounds: Rectangle,
     rotate: i32,
+    invert: bool,
+    // Shapes in the coordinates of the wrapped display
+    clip: Vec<RoundedRectangle>,
+    style: Style,
 }
 
 impl<'b, D, TargetColor, ErrorType> ShiftableDisplay<D, TargetColor, ErrorType>
@@ -65,11 +113,24 @@ where
                 },
             },
         };
-        let outer_bounds = Rectangle {
-            top_left: embedded_graphics::prelude::Point {
+        // Without rotation, the new display covers the bounds as drawn on this one
+        let top_left = match (rotate, self.to_outer(bounds.top_left)) {
+            (0, Some(corner)) => {
+                corner
+                    - rotate_point(
+                        embedded_graphics::prelude::Point::zero(),
+                        self.rotate,
+                        inner_bounds.size,
+                    )
+                    .unwrap_or_default()
+            }
+            _ => embedded_graphics::prelude::Point {
                 x: self.outer_bounds.top_left.x + bounds.top_left.x,
                 y: self.outer_bounds.top_left.y + bounds.top_left.y,
             },
+        };
+        let outer_bounds = Rectangle {
+            top_left,
             size: bounds.size.clone().into(),
         };
 
@@ -78,6 +139,9 @@ where
             inner_bounds,
             outer_bounds,
             rotate: (self.rotate + rotate) % 4,
+            invert: self.invert,
+            clip: self.clip.clone(),
+            style: self.style.clone(),
         }
     }
 }
@@ -100,7 +164,86 @@ where
             inner_bounds,
             outer_bounds,
             rotate: 0,
+            invert: false,
+            clip: vec![],
+            style: Style::default(),
+        }
+    }
+
+    /// Defaults of the primitives drawn on the display
+    pub fn style(&self) -> &Style {
+        &self.style
+    }
+
+    /// Override the inherited defaults
+    pub fn with_style(mut self, style: &Style) -> Self {
+        self.style = style.over(&self.style);
+        self
+    }
+
+    /// Invert the colors drawn on the display
+    pub fn inverted(mut self) -> Self {
+        self.invert = !self.invert;
+        self
+    }
+
+    /// Only draw the pixels inside the shape, given in the coordinates of this display
+    pub fn clipped_to(mut self, shape: RoundedRectangle) -> Self {
+        let bounds = shape.bounding_box();
+        if let (Some(top_left), Some(bottom_right)) = (
+            self.to_outer(bounds.top_left),
+            bounds.bottom_right().and_then(|p| self.to_outer(p)),
+        ) {
+            // Corners are equal, so rotating the shape only swaps its sides
+            self.clip.push(RoundedRectangle::new(
+                Rectangle::with_corners(top_left, bottom_right),
+                shape.corners,
+            ));
         }
+        self
+    }
+
+    /// Use origin as the coordinates of the top left corner of the display
+    pub fn with_origin(mut self, origin: embedded_graphics::prelude::Point) -> Self {
+        self.inner_bounds.top_left = origin;
+        self
+    }
+
+    /// Position on the wrapped display of a point of this one
+    fn to_outer(
+        &self,
+        point: embedded_graphics::prelude::Point,
+    ) -> Option<embedded_graphics::prelude::Point> {
+        rotate_point(
+            point - self.inner_bounds.top_left,
+            self.rotate,
+            self.inner_bounds.size,
+        )
+        .map(|p| p + self.outer_bounds.top_left)
+    }
+}
+
+// Rotation of a point of an area of the given size (before rotation)
+fn rotate_point(
+    point: embedded_graphics::prelude::Point,
+    rotate: i32,
+    size: embedded_graphics::prelude::Size,
+) -> Option<embedded_graphics::prelude::Point> {
+    match rotate {
+        0 => Some(point),
+        1 => Some(embedded_graphics::prelude::Point {
+            x: point.y,
+            y: size.width as i32 - point.x,
+        }),
+        2 => Some(embedded_graphics::prelude::Point {
+            x: size.width as i32 - point.x,
+            y: size.height as i32 - point.y,
+        }),
+        3 => Some(embedded_graphics::prelude::Point {
+            x: size.height as i32 - point.y,
+            y: point.x,
+        }),
+        _ => None,
     }
 }
 /*{
@@ -146,51 +289,30 @@ where
     where
         I: IntoIterator<Item = Pixel<Self::Color>>,
     {
+        let (inner_bounds, outer_top_left, rotate, invert) = (
+            self.inner_bounds,
+            self.outer_bounds.top_left,
+            self.rotate,
+            self.invert,
+        );
+        let clip = &self.clip;
         self.display
             .draw_iter(pixels.into_iter().filter_map(|pixel| {
-                if pixel.0.x < 0 || pixel.0.y < 0 {
+                let local = pixel.0 - inner_bounds.top_left;
+                if local.x < 0 || local.y < 0 {
                     return None;
                 }
-                if pixel.0.x >= self.inner_bounds.size.width as i32
-                    || pixel.0.y >= self.inner_bounds.size.height as i32
+                if local.x >= inner_bounds.size.width as i32
+                    || local.y >= inner_bounds.size.height as i32
                 {
                     return None;
                 }
-                match self.rotate {
-                    0 => Some(Pixel(
-                        embedded_graphics::prelude::Point {
-                            x: pixel.0.x + self.outer_bounds.top_left.x,
-                            y: pixel.0.y + self.outer_bounds.top_left.y,
-                        },
-                        pixel.1,
-                    )),
-                    1 => Some(Pixel(
-                        embedded_graphics::prelude::Point {
-                            x: pixel.0.y + self.outer_bounds.top_left.x,
-                            y: self.inner_bounds.size.width as i32 - pixel.0.x
-                                + self.outer_bounds.top_left.y,
-                        },
-                        pixel.1,
-                    )),
-                    2 => Some(Pixel(
-                        embedded_graphics::prelude::Point {
-                            x: self.inner_bounds.size.width as i32 - pixel.0.x
-                                + self.outer_bounds.top_left.x,
-                            y: self.inner_bounds.size.height as i32 - pixel.0.y
-                                + self.outer_bounds.top_left.y,
-                        },
-                        pixel.1,
-                    )),
-                    3 => Some(Pixel(
-                        embedded_graphics::prelude::Point {
-                            x: self.inner_bounds.size.height as i32 - pixel.0.y
-                                + self.outer_bounds.top_left.x,
-                            y: pixel.0.x + self.outer_bounds.top_left.y,
-                        },
-                        pixel.1,
-                    )),
-                    _ => None,
+                let point = rotate_point(local, rotate, inner_bounds.size)? + outer_top_left;
+                if !clip.iter().all(|shape| shape.contains(point)) {
+                    return None;
                 }
+                let color = if invert { pixel.1.invert() } else { pixel.1 };
+                Some(Pixel(point, color))
             }))?;
 
         Ok(())
@@ -205,18 +327,112 @@ where
     D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
     TargetColor: PixelColor + ColorFromTemplate,
 {
+    let size = match &container.size {
+        Some(size) => size.clone().into(),
+        None => {
+            let parent = display.bounding_box();
+            let bottom_right = parent.top_left + parent.size;
+            embedded_graphics::prelude::Size::new(
+                (bottom_right.x - container.position.x).max(0) as u32,
+                (bottom_right.y - container.position.y).max(0) as u32,
+            )
+        }
+    };
     let outer_bounds = Rectangle {
         top_left: container.position.clone().into(),
-        size: container.size.clone().into(),
+        size,
     };
 
     let mut shifted_display = display.shift(outer_bounds.clone(), container.rotate.unwrap_or(0));
-    super::draw(
-        &mut shifted_display,
-        &container.content.clone().unwrap_or_default(),
-    )?;
+    let content = container.content.clone().unwrap_or_default();
+
+    if let Some(style) = &container.style {
+        shifted_display = shifted_display.with_style(style);
+    }
+
+    let invert = container.invert.unwrap_or(false);
+    if invert {
+        shifted_display = shifted_display.inverted();
+    }
+
+    let frame = shifted_display.bounding_box();
+    let radius = container.radius.unwrap_or(0);
+    let shape = RoundedRectangle::with_equal_corners(
+        frame,
+        embedded_graphics::prelude::Size::new_equal(radius),
+    );
+    if radius > 0 {
+        shifted_display = shifted_display.clipped_to(shape);
+    }
+    if container.background.is_some() || invert {
+        let (back, _) = resolve_colors::<TargetColor>(&None, &container.background);
+        shape
+            .into_styled(PrimitiveStyle::with_fill(back))
+            .draw(&mut shifted_display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+    }
+    let border = container.border.unwrap_or(0);
+    if border > 0 {
+        shape
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .stroke_color(TargetColor::resolve(&container.border_color))
+                    .stroke_width(border)
+                    .stroke_alignment(StrokeAlignment::Inside)
+                    .build(),
+            )
+            .draw(&mut shifted_display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+    }
+
+    // The content stays inside the border
+    let padding = container.padding.unwrap_or(0) + border;
+    let area = Rectangle::new(
+        embedded_graphics::prelude::Point::new(padding as i32, padding as i32),
+        shifted_display
+            .bounding_box()
+            .size
+            .saturating_sub(embedded_graphics::prelude::Size::new_equal(2 * padding)),
+    );
 
-    Ok(())
+    let layout = container.layout.unwrap_or(Layout::Absolute);
+    if layout == Layout::Absolute {
+        if padding == 0 {
+            return super::draw(&mut shifted_display, &content);
+        }
+        return super::draw(&mut shifted_display.shift(area, 0), &content);
+    }
+
+    // Children are measured with their relative sizes taken from the content area, and their style
+    let content: Vec<Primitive> = content
+        .iter()
+        .filter(|c| !matches!(c, Primitive::Dummy(_)))
+        .map(|c| {
+            let mut child = resolve_relative(
+                c,
+                &Rectangle::new(embedded_graphics::prelude::Point::zero(), area.size),
+            )
+            .into_owned();
+            shifted_display.style().apply(&mut child);
+            child
+        })
+        .collect();
+    let children: Vec<&Primitive> = content.iter().collect();
+    let bounds: Vec<Rectangle> = children.iter().map(|c| measure(c)).collect();
+    let slots = flow(container, layout, area.size, &children, &bounds);
+
+    // Each child is drawn in its slot, the top left corner of its bounds at the slot origin
+    let mut result = Ok(());
+    for ((child, bounds), slot) in children.iter().zip(bounds.iter()).zip(slots.iter()) {
+        let mut child_display = shifted_display
+            .shift(Rectangle::new(area.top_left + slot.top_left, slot.size), 0)
+            .with_origin(bounds.top_left);
+        let problem = super::draw(&mut child_display, std::slice::from_ref(*child));
+        if result.is_ok() {
+            result = problem;
+        }
+    }
+    result
 }
 
 #[cfg(test)]
@@ -232,19 +448,30 @@ mod tests {
     #[test]
     fn test_yaml_parsing() {
         let example = Primitive::Container(Container {
-            size: Size {
+            size: Some(Size {
                 width: 60,
                 height: 28,
+                ..Default::default()
+            }),
+            position: Point {
+                x: 2,
+                y: 2,
+                ..Default::default()
             },
-            position: Point { x: 2, y: 2 },
             rotate: Some(1),
             content: Some(vec![Primitive::Text(TextItem {
                 value: format!("Hi{}!", 1).to_string(),
-                position: Point { x: 0, y: 0 },
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
                 font: Some("4x6".to_string()),
                 color: Some("0".to_string()),
                 align: Some(Alignment::Left),
+                ..Default::default()
             })]),
+            ..Default::default()
         });
 
         let yaml = serde_yaml::to_string(&example).unwrap();
@@ -283,19 +510,30 @@ content:
             (0..4)
                 .map(|r| {
                     Primitive::Container(Container {
-                        size: Size {
+                        size: Some(Size {
                             width: 60,
                             height: 28,
+                            ..Default::default()
+                        }),
+                        position: Point {
+                            x: 2,
+                            y: 2,
+                            ..Default::default()
                         },
-                        position: Point { x: 2, y: 2 },
                         rotate: Some(r),
                         content: Some(vec![Primitive::Text(TextItem {
                             value: format!("Hi{}!", r).to_string(),
-                            position: Point { x: 0, y: 0 },
+                            position: Point {
+                                x: 0,
+                                y: 0,
+                                ..Default::default()
+                            },
                             font: Some("4x6".to_string()),
                             color: Some("0".to_string()),
                             align: Some(Alignment::Left),
+                            ..Default::default()
                         })]),
+                        ..Default::default()
                     })
                 })
                 .collect(),
@@ -321,6 +559,192 @@ content:
 ████▄█▄██████████████████████████████████████████▄██▄ ▄█▄ ▄█ █ █
 ██▄▄ ▄▄██████████████████████████████████████████ ██ █▀██▄▄█ ▄ █
 ██▄▄▄▄▄██████████████████████████████████████████▄███▄███▄██▄█▄█
+"#
+        );
+    }
+
+    #[test]
+    fn test_row_layout() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 48,
+                height: 12,
+            },
+            vec![Primitive::Container(Container {
+                size: Some(Size {
+                    width: 48,
+                    height: 12,
+                    ..Default::default()
+                }),
+                layout: Some(Layout::Row),
+                padding: Some(1),
+                spacing: Some(2),
+                align_items: Some(ItemAlignment::Center),
+                content: Some(vec![
+                    Primitive::Text(TextItem {
+                        value: "Hi".to_string(),
+                        font: Some("4x6".to_string()),
+                        color: Some("0".to_string()),
+                        ..Default::default()
+                    }),
+                    Primitive::Container(Container {
+                        layout: Some(Layout::Row),
+                        justify: Some(Justify::End),
+                        grow: Some(1),
+                        content: Some(vec![Primitive::Text(TextItem {
+                            value: "OK".to_string(),
+                            font: Some("4x6".to_string()),
+                            color: Some("0".to_string()),
+                            ..Default::default()
+                        })]),
+                        ..Default::default()
+                    }),
+                    Primitive::Progress(Progress {
+                        width: 8.into(),
+                        height: 8.into(),
+                        value: Some(1.0),
+                        color: Some("0".to_string()),
+                        ..Default::default()
+                    }),
+                ]),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████████████████████████████████████
+█▀█▀██▀███████████████████████▀██▀█▀███        █
+█ ▀ █▀▀██████████████████████ █ █ ▀▄███        █
+█ █ █▀ ▀█████████████████████▄▀▄█ █ ███        █
+███████████████████████████████████████        █
+████████████████████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_column_layout_rotated() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 16,
+                height: 24,
+            },
+            vec![Primitive::Container(Container {
+                size: Some(Size {
+                    width: 16,
+                    height: 24,
+                    ..Default::default()
+                }),
+                rotate: Some(1),
+                layout: Some(Layout::Column),
+                spacing: Some(1),
+                align_items: Some(ItemAlignment::End),
+                content: Some(vec![
+                    Primitive::Text(TextItem {
+                        value: "A".to_string(),
+                        font: Some("4x6".to_string()),
+                        color: Some("0".to_string()),
+                        ..Default::default()
+                    }),
+                    Primitive::Text(TextItem {
+                        value: "BCD".to_string(),
+                        font: Some("4x6".to_string()),
+                        color: Some("0".to_string()),
+                        ..Default::default()
+                    }),
+                ]),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████
+▀▄ ▄▄██▀▄▄▄▀████
+█▄▄▄▄██▄▄▄▄▄████
+███████▀▄█▄▀████
+████████▄▄▄█████
+███████▀▄▀▄▀████
+███████▄▄▄▄▄████
+████████████████
+████████████████
+████████████████
+████████████████
+████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_decorations() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 40,
+                height: 24,
+            },
+            vec![
+                Primitive::Container(Container {
+                    size: Some(Size {
+                        width: 40,
+                        height: 10,
+                        ..Default::default()
+                    }),
+                    radius: Some(3),
+                    padding: Some(2),
+                    invert: Some(true),
+                    content: Some(vec![Primitive::Text(TextItem {
+                        value: "Header".to_string(),
+                        font: Some("4x6".to_string()),
+                        color: Some("0".to_string()),
+                        ..Default::default()
+                    })]),
+                    ..Default::default()
+                }),
+                Primitive::Container(Container {
+                    position: Point {
+                        x: 0,
+                        y: 12,
+                        ..Default::default()
+                    },
+                    size: Some(Size {
+                        width: 24,
+                        height: 12,
+                        ..Default::default()
+                    }),
+                    border: Some(2),
+                    padding: Some(1),
+                    content: Some(vec![Primitive::Text(TextItem {
+                        value: "Box".to_string(),
+                        font: Some("4x6".to_string()),
+                        color: Some("0".to_string()),
+                        ..Default::default()
+                    })]),
+                    ..Default::default()
+                }),
+            ],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+▀                                      ▀
+  █ █  ▄   ▄▄  ▄█  ▄  ▄ ▄               
+  █▀█ █▄▀ █ █ █ █ █▄▀ █▀                
+  ▀ ▀  ▀▀  ▀▀  ▀▀  ▀▀ ▀                 
+▄                                      ▄
+████████████████████████████████████████
+                        ████████████████
+  █▀▀█████████████████  ████████████████
+  █ ▀▄█▀▄▀█▄▀▄████████  ████████████████
+  █ ▀▄█▄▀▄█▀▄▀████████  ████████████████
+  ████████████████████  ████████████████
+                        ████████████████
 "#
         );
     }
@@ -0,0 +1,594 @@
+use crate::error::DrawingError;
+
+use png::Transformations;
+
+use super::raster::Raster;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Png,
+    Gif,
+    Jpeg,
+    Bmp,
+    /// PBM, PGM and PPM, by their magic number (1 to 6)
+    Netpbm(u8),
+}
+
+fn detect(bytes: &[u8]) -> Option<Format> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some(Format::Png),
+        [b'G', b'I', b'F', b'8', ..] => Some(Format::Gif),
+        [0xff, 0xd8, 0xff, ..] => Some(Format::Jpeg),
+        [b'B', b'M', ..] => Some(Format::Bmp),
+        [b'P', kind @ b'1'..=b'6', ..] => Some(Format::Netpbm(kind - b'0')),
+        _ => None,
+    }
+}
+
+/// Largest image decoded, so that bad headers cannot exhaust the memory
+const MAX_PIXELS: usize = 1 << 26;
+
+// Number of samples of the image, checked against overflows and the pixel limit
+fn sample_count(width: u32, height: u32, channels: u32) -> Result<usize, String> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .filter(|pixels| *pixels <= MAX_PIXELS)
+        .and_then(|pixels| pixels.checked_mul(channels as usize))
+        .ok_or_else(|| "image too large".to_string())
+}
+
+/// Reduce a color to its brightest channel
+pub(super) fn gray(r: u8, g: u8, b: u8) -> u8 {
+    r.max(g).max(b)
+}
+
+/// Decode an image file, whatever its format
+pub fn decode(path: &str, bytes: &[u8]) -> Result<Raster, DrawingError> {
+    let invalid = |reason: String| DrawingError::InvalidImage(path.to_string(), reason);
+    match detect(bytes) {
+        Some(Format::Png) => {
+            decode_png(bytes).map_err(|e| DrawingError::ImageError(path.to_string(), e))
+        }
+        Some(Format::Gif) => decode_gif(bytes).map_err(|e| invalid(e.to_string())),
+        Some(Format::Jpeg) => decode_jpeg(bytes).map_err(|e| invalid(e.to_string())),
+        Some(Format::Bmp) => decode_bmp(bytes).map_err(invalid),
+        Some(Format::Netpbm(kind)) => decode_netpbm(kind, bytes).map_err(invalid),
+        None => Err(invalid("unknown image format".to_string())),
+    }
+}
+
+/// Bytes of inline image data, given as base64 or as a data URI
+pub fn decode_data(data: &str) -> Result<Vec<u8>, String> {
+    let data = data.trim();
+    match data.strip_prefix("data:") {
+        Some(uri) => {
+            let (header, payload) = uri
+                .split_once(',')
+                .ok_or_else(|| "data URI without a comma".to_string())?;
+            match header.ends_with(";base64") {
+                true => decode_base64(payload),
+                false => percent_decode(payload),
+            }
+        }
+        None => decode_base64(data),
+    }
+}
+
+fn decode_base64(data: &str) -> Result<Vec<u8>, String> {
+    use base64::engine::{general_purpose, DecodePaddingMode, GeneralPurpose};
+    use base64::Engine;
+    const ENGINE: GeneralPurpose = GeneralPurpose::new(
+        &base64::alphabet::STANDARD,
+        general_purpose::NO_PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+    let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    ENGINE.decode(data).map_err(|e| e.to_string())
+}
+
+fn percent_decode(data: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(data.len());
+    let mut chars = data.bytes();
+    while let Some(c) = chars.next() {
+        if c != b'%' {
+            bytes.push(c);
+            continue;
+        }
+        let hex = [chars.next(), chars.next()];
+        let value = match hex {
+            [Some(high), Some(low)] => std::str::from_utf8(&[high, low])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        bytes.push(value.ok_or_else(|| "invalid percent escape".to_string())?);
+    }
+    Ok(bytes)
+}
+
+/// Packed 1-bit rows, padded to a byte, with 1 for black as in PBM files
+pub fn decode_bitmap(width: u32, height: u32, bytes: &[u8]) -> Result<Raster, String> {
+    sample_count(width, height, 1)?;
+    match width.div_ceil(8).checked_mul(height) {
+        Some(size) if size as usize <= bytes.len() => {}
+        Some(_) => return Err("truncated bitmap".to_string()),
+        None => return Err("image too large".to_string()),
+    }
+    let mut file = format!("P4 {} {}\n", width, height).into_bytes();
+    file.extend_from_slice(bytes);
+    decode_netpbm(4, &file)
+}
+
+fn decode_png(bytes: &[u8]) -> Result<Raster, png::DecodingError> {
+    let mut decoder = png::Decoder::new(bytes);
+    // Palettes are expanded, low bit depths scaled up and 16 bits reduced to 8
+    decoder.set_transformations(Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    // Allocate the output buffer.
+    let mut buf = vec![0; reader.output_buffer_size()];
+    // Read the next frame. An APNG might contain multiple frames.
+    let info = reader.next_frame(&mut buf)?;
+    // Grab the bytes of the image.
+    let bytes = &buf[..info.buffer_size()];
+
+    let samples = info.color_type.samples();
+    Ok(Raster::from_fn(info.width, info.height, |x, y| {
+        let pos = y as usize * info.line_size + x as usize * samples;
+        let p = &bytes[pos..pos + samples];
+        match info.color_type {
+            png::ColorType::Grayscale => [p[0], 255],
+            png::ColorType::GrayscaleAlpha => [p[0], p[1]],
+            png::ColorType::Indexed => [if p[0] != 0 { 255 } else { 0 }, 255],
+            png::ColorType::Rgb => [gray(p[0], p[1], p[2]), 255],
+            png::ColorType::Rgba => [gray(p[0], p[1], p[2]), p[3]],
+        }
+    }))
+}
+
+/// First frame of the animation, on the logical screen
+fn decode_gif(bytes: &[u8]) -> Result<Raster, gif::DecodingError> {
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::RGBA);
+    let mut decoder = options.read_info(bytes)?;
+    let (width, height) = (decoder.width() as u32, decoder.height() as u32);
+    let frame = match decoder.read_next_frame()? {
+        Some(frame) => frame,
+        None => return Ok(Raster::from_fn(width, height, |_, _| [0, 0])),
+    };
+    let (left, top) = (frame.left as u32, frame.top as u32);
+    let (frame_width, frame_height) = (frame.width as u32, frame.height as u32);
+    Ok(Raster::from_fn(width, height, |x, y| {
+        if x < left || y < top || x >= left + frame_width || y >= top + frame_height {
+            return [0, 0];
+        }
+        let pos = (((y - top) * frame_width + x - left) * 4) as usize;
+        let p = &frame.buffer[pos..pos + 4];
+        [gray(p[0], p[1], p[2]), p[3]]
+    }))
+}
+
+fn decode_jpeg(bytes: &[u8]) -> Result<Raster, jpeg_decoder::Error> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode()?;
+    let info = decoder
+        .info()
+        .ok_or_else(|| jpeg_decoder::Error::Format("missing header".to_string()))?;
+    let (width, height) = (info.width as u32, info.height as u32);
+    let at = |x: u32, y: u32, size: u32| ((y * width + x) * size) as usize;
+    Ok(Raster::from_fn(width, height, |x, y| {
+        let gray = match info.pixel_format {
+            jpeg_decoder::PixelFormat::L8 => pixels[at(x, y, 1)],
+            // Big endian, keep the high byte
+            jpeg_decoder::PixelFormat::L16 => pixels[at(x, y, 2)],
+            jpeg_decoder::PixelFormat::RGB24 => {
+                let p = &pixels[at(x, y, 3)..];
+                gray(p[0], p[1], p[2])
+            }
+            jpeg_decoder::PixelFormat::CMYK32 => {
+                let p = &pixels[at(x, y, 4)..];
+                let ink = |c: u8| ((255 - c as u32) * (255 - p[3] as u32) / 255) as u8;
+                gray(ink(p[0]), ink(p[1]), ink(p[2]))
+            }
+        };
+        [gray, 255]
+    }))
+}
+
+fn decode_bmp(bytes: &[u8]) -> Result<Raster, String> {
+    let truncated = || "truncated file".to_string();
+    let u16_at = |pos: usize| {
+        bytes
+            .get(pos..pos + 2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .ok_or_else(truncated)
+    };
+    let u32_at = |pos: usize| {
+        bytes
+            .get(pos..pos + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(truncated)
+    };
+
+    let data = u32_at(10)? as usize;
+    let header = u32_at(14)? as usize;
+    let width = u32_at(18)? as i32;
+    let height = u32_at(22)? as i32;
+    let bits = u16_at(28)? as u32;
+    let compression = u32_at(30)?;
+    // Red, green and blue masks. Bit fields are only allowed for 16 and 32 bits
+    let masks = match (compression, bits) {
+        (0, 16) => [0x7c00, 0x03e0, 0x001f],
+        (0, _) => [0xff0000, 0x00ff00, 0x0000ff],
+        (3, 16 | 32) => [u32_at(54)?, u32_at(58)?, u32_at(62)?],
+        _ => {
+            return Err(format!(
+                "unsupported compression {} for {} bits",
+                compression, bits
+            ))
+        }
+    };
+    if masks.contains(&0) {
+        return Err("invalid bit fields".to_string());
+    }
+    if width <= 0 || height == 0 {
+        return Err(format!("invalid size {}x{}", width, height));
+    }
+    // Rows are stored bottom up, unless the height is negative
+    let bottom_up = height > 0;
+    let (width, height) = (width as u32, height.unsigned_abs());
+    // Only V4 and later headers tell where alpha is
+    let alpha = bits == 32 && header >= 56 && u32_at(14 + 52)? != 0;
+
+    let palette: Vec<u8> = if bits <= 8 {
+        let colors = match u32_at(46)? {
+            0 => 1 << bits,
+            colors => colors,
+        };
+        (0..colors as usize)
+            .map(|i| {
+                let entry = 14 + header + 4 * i;
+                bytes
+                    .get(entry..entry + 3)
+                    .map(|p| gray(p[2], p[1], p[0]))
+                    .ok_or_else(truncated)
+            })
+            .collect::<Result<_, _>>()?
+    } else {
+        vec![]
+    };
+
+    sample_count(width, height, 1)?;
+    let too_large = || "image too large".to_string();
+    let stride = (width as usize)
+        .checked_mul(bits as usize)
+        .ok_or_else(too_large)?
+        .div_ceil(32)
+        * 4;
+    let end = stride
+        .checked_mul(height as usize)
+        .and_then(|size| size.checked_add(data))
+        .ok_or_else(too_large)?;
+    if bytes.len() < end {
+        return Err(truncated());
+    }
+    // Channel under the mask, scaled to 8 bits
+    let channel = |value: u32, mask: u32| {
+        let shift = mask.trailing_zeros();
+        (((value & mask) >> shift) as u64 * 255 / (mask >> shift) as u64) as u8
+    };
+    let pixel = |x: u32, y: u32| -> [u8; 2] {
+        let row = if bottom_up { height - 1 - y } else { y };
+        let line = &bytes[data + row as usize * stride..];
+        match bits {
+            1 | 2 | 4 | 8 => {
+                let bit = x * bits;
+                let index =
+                    (line[(bit / 8) as usize] >> (8 - bits - bit % 8)) & ((1 << bits) - 1) as u8;
+                [palette.get(index as usize).copied().unwrap_or(0), 255]
+            }
+            16 => {
+                let p = &line[2 * x as usize..];
+                let v = u16::from_le_bytes([p[0], p[1]]) as u32;
+                let [r, g, b] = masks.map(|mask| channel(v, mask));
+                [gray(r, g, b), 255]
+            }
+            24 => {
+                let p = &line[3 * x as usize..];
+                [gray(p[2], p[1], p[0]), 255]
+            }
+            _ => {
+                let p = &line[4 * x as usize..];
+                let v = u32::from_le_bytes([p[0], p[1], p[2], p[3]]);
+                let [r, g, b] = masks.map(|mask| channel(v, mask));
+                [gray(r, g, b), if alpha { p[3] } else { 255 }]
+            }
+        }
+    };
+    match bits {
+        1 | 2 | 4 | 8 | 16 | 24 | 32 => Ok(Raster::from_fn(width, height, pixel)),
+        _ => Err(format!("unsupported bit count {}", bits)),
+    }
+}
+
+fn decode_netpbm(kind: u8, bytes: &[u8]) -> Result<Raster, String> {
+    // Header fields are separated by white space, and comments run to the end of line
+    let mut pos = 2;
+    let mut field = || -> Result<u32, String> {
+        loop {
+            match bytes.get(pos) {
+                Some(b'#') => {
+                    while !matches!(bytes.get(pos), None | Some(b'\n')) {
+                        pos += 1;
+                    }
+                }
+                Some(c) if c.is_ascii_whitespace() => pos += 1,
+                _ => break,
+            }
+        }
+        let start = pos;
+        while bytes.get(pos).is_some_and(|c| c.is_ascii_digit()) {
+            pos += 1;
+        }
+        std::str::from_utf8(&bytes[start..pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| "invalid header".to_string())
+    };
+
+    let width = field()?;
+    let height = field()?;
+    // Bitmaps have no maximum value
+    let max = match kind {
+        1 | 4 => 1,
+        _ => field()?.max(1),
+    };
+    let channels = match kind {
+        3 | 6 => 3,
+        _ => 1,
+    };
+    let count = sample_count(width, height, channels)?;
+
+    let samples: Vec<u32> = match kind {
+        1 => {
+            let bits: Vec<u32> = bytes[pos..]
+                .iter()
+                .filter_map(|c| match c {
+                    b'0' => Some(0),
+                    b'1' => Some(1),
+                    _ => None,
+                })
+                .take(count)
+                .collect();
+            if bits.len() < count {
+                return Err("truncated file".to_string());
+            }
+            bits
+        }
+        // Plain formats list the samples in decimal. Bits may be written without spaces
+        2 | 3 => (0..count).map(|_| field()).collect::<Result<_, _>>()?,
+        // Raw bitmaps pack 8 pixels per byte, rows are padded to a byte
+        4 => {
+            let data = bytes.get(pos + 1..).unwrap_or_default();
+            let stride = width.div_ceil(8) as usize;
+            if data.len() < stride * height as usize {
+                return Err("truncated file".to_string());
+            }
+            (0..height as usize)
+                .flat_map(|y| (0..width as usize).map(move |x| (x, y)))
+                .map(|(x, y)| ((data[y * stride + x / 8] >> (7 - x % 8)) & 1) as u32)
+                .collect()
+        }
+        _ => {
+            let data = bytes.get(pos + 1..).unwrap_or_default();
+            let size = if max > 255 { 2 } else { 1 };
+            if data.len() < count * size {
+                return Err("truncated file".to_string());
+            }
+            (0..count)
+                .map(|i| match size {
+                    2 => u16::from_be_bytes([data[2 * i], data[2 * i + 1]]) as u32,
+                    _ => data[i] as u32,
+                })
+                .collect()
+        }
+    };
+
+    let scale = |v: u32| (v.min(max) * 255 / max) as u8;
+    Ok(Raster::from_fn(width, height, |x, y| {
+        let i = ((y * width + x) * channels) as usize;
+        let gray = match kind {
+            // In bitmaps, 1 is black
+            1 | 4 => 255 - scale(samples[i]),
+            3 | 6 => gray(
+                scale(samples[i]),
+                scale(samples[i + 1]),
+                scale(samples[i + 2]),
+            ),
+            _ => scale(samples[i]),
+        };
+        [gray, 255]
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data() {
+        assert_eq!(decode_data("UDEgMSAx").unwrap(), b"P1 1 1");
+        assert_eq!(decode_data(" UDEg\nMSAx IDE= ").unwrap(), b"P1 1 1 1");
+        assert_eq!(decode_data("UDEgMSAxIDE").unwrap(), b"P1 1 1 1");
+        assert_eq!(
+            decode_data("data:image/x-portable-bitmap;base64,UDEgMSAx").unwrap(),
+            b"P1 1 1"
+        );
+        assert_eq!(
+            decode_data("data:image/svg+xml;utf8,%3Csvg%2F%3E").unwrap(),
+            b"<svg/>"
+        );
+        assert!(decode_data("data:image/png;base64").is_err());
+        assert!(decode_data("not base64!").is_err());
+
+        let bitmap = decode_bitmap(3, 2, &[0b1010_0000, 0b0110_0000]).unwrap();
+        assert_eq!(gray(&bitmap), vec![0, 255, 0, 255, 0, 0]);
+        assert!(decode_bitmap(9, 1, &[0]).is_err());
+        assert_eq!(
+            decode_bitmap(u32::MAX, u32::MAX, &[0]).err(),
+            Some("image too large".to_string())
+        );
+    }
+
+    fn gray(raster: &Raster) -> Vec<u8> {
+        raster.pixels.iter().map(|[g, _]| *g).collect()
+    }
+
+    fn png(bit_depth: png::BitDepth, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, 4, 1);
+        encoder.set_depth(bit_depth);
+        encoder.set_color(color);
+        if color == png::ColorType::Indexed {
+            encoder.set_palette(vec![0, 0, 0, 255, 255, 255]);
+        }
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(data).unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_png_depths() {
+        use png::{BitDepth, ColorType};
+        // Black, white, black, white
+        let cases = [
+            png(BitDepth::One, ColorType::Grayscale, &[0b0101_0000]),
+            png(BitDepth::Two, ColorType::Grayscale, &[0b0011_0011]),
+            png(BitDepth::Four, ColorType::Grayscale, &[0x0f, 0x0f]),
+            png(BitDepth::Eight, ColorType::Grayscale, &[0, 255, 0, 255]),
+            png(
+                BitDepth::Sixteen,
+                ColorType::Grayscale,
+                &[0, 0, 255, 255, 0, 0, 255, 255],
+            ),
+            png(BitDepth::One, ColorType::Indexed, &[0b0101_0000]),
+        ];
+        for bytes in cases {
+            assert_eq!(
+                gray(&decode("test.png", &bytes).unwrap()),
+                vec![0, 255, 0, 255]
+            );
+        }
+    }
+
+    #[test]
+    fn test_netpbm() {
+        let pbm = decode("test.pbm", b"P1\n# comment\n4 1\n1010").unwrap();
+        assert_eq!(gray(&pbm), vec![0, 255, 0, 255]);
+
+        let pbm = decode("test.pbm", b"P4 4 1\n\xa0").unwrap();
+        assert_eq!(gray(&pbm), vec![0, 255, 0, 255]);
+
+        let pgm = decode("test.pgm", b"P2 2 1 15 0 15").unwrap();
+        assert_eq!(gray(&pgm), vec![0, 255]);
+
+        let pgm = decode("test.pgm", b"P5 2 1 65535\n\x00\x00\xff\xff").unwrap();
+        assert_eq!(gray(&pgm), vec![0, 255]);
+
+        let ppm = decode("test.ppm", b"P6 2 1 255\n\x00\x00\x00\x00\x80\x00").unwrap();
+        assert_eq!(gray(&ppm), vec![0, 128]);
+
+        assert!(decode("test.pgm", b"P5 2 1 255\n\x00").is_err());
+        for header in [&b"P5 65536 65536 255\n"[..], b"P4 4294967295 4294967295\n"] {
+            assert!(matches!(
+                decode("test.pgm", header),
+                Err(DrawingError::InvalidImage(_, reason)) if reason == "image too large"
+            ));
+        }
+    }
+
+    // BMP file with a 40 bytes header, followed by the masks when compression is 3
+    fn bmp(width: i32, height: i32, bits: u16, compression: u32, masks: &[u32]) -> Vec<u8> {
+        let data = 54 + 4 * masks.len() as u32;
+        let mut bmp = vec![b'B', b'M'];
+        bmp.extend_from_slice(&0u32.to_le_bytes());
+        bmp.extend_from_slice(&[0, 0, 0, 0]);
+        bmp.extend_from_slice(&data.to_le_bytes());
+        bmp.extend_from_slice(&40u32.to_le_bytes());
+        bmp.extend_from_slice(&width.to_le_bytes());
+        bmp.extend_from_slice(&height.to_le_bytes());
+        bmp.extend_from_slice(&1u16.to_le_bytes());
+        bmp.extend_from_slice(&bits.to_le_bytes());
+        bmp.extend_from_slice(&compression.to_le_bytes());
+        bmp.extend_from_slice(&[0; 20]);
+        for mask in masks {
+            bmp.extend_from_slice(&mask.to_le_bytes());
+        }
+        bmp
+    }
+
+    #[test]
+    fn test_bmp() {
+        // 2x2, 24 bits, bottom up: the first row in the file is the bottom one
+        let mut file = bmp(2, 2, 24, 0, &[]);
+        file.extend_from_slice(&[255, 255, 255, 0, 0, 0, 0, 0]);
+        file.extend_from_slice(&[0, 0, 0, 0, 0, 255, 0, 0]);
+        assert_eq!(
+            gray(&decode("test.bmp", &file).unwrap()),
+            vec![0, 255, 255, 0]
+        );
+
+        // RGB565: pure green is full scale, half of the red is a middle gray
+        let mut file = bmp(2, -1, 16, 3, &[0xf800, 0x07e0, 0x001f]);
+        file.extend_from_slice(&0x07e0u16.to_le_bytes());
+        file.extend_from_slice(&0x8000u16.to_le_bytes());
+        assert_eq!(gray(&decode("test.bmp", &file).unwrap()), vec![255, 131]);
+
+        assert!(decode("test.bmp", &bmp(2, 2, 24, 3, &[0xff, 0xff00, 0xff0000])).is_err());
+        assert!(decode("test.bmp", &bmp(2, 2, 16, 3, &[0, 0, 0])).is_err());
+        assert!(decode("test.bmp", &bmp(0x7fff_ffff, 0x7fff_ffff, 32, 0, &[])).is_err());
+    }
+
+    #[test]
+    fn test_gif() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder =
+                gif::Encoder::new(&mut bytes, 3, 1, &[0, 0, 0, 255, 255, 255]).unwrap();
+            // The frame covers the two last pixels of the screen
+            let frame = gif::Frame {
+                left: 1,
+                width: 2,
+                height: 1,
+                buffer: std::borrow::Cow::Borrowed(&[1, 0]),
+                ..Default::default()
+            };
+            encoder.write_frame(&frame).unwrap();
+        }
+        let raster = decode("test.gif", &bytes).unwrap();
+        assert_eq!(raster.pixels, vec![[0, 0], [255, 255], [0, 255]]);
+    }
+
+    #[test]
+    fn test_jpeg() {
+        let mut bytes = Vec::new();
+        let encoder = jpeg_encoder::Encoder::new(&mut bytes, 100);
+        let pixels: Vec<u8> = (0..16 * 8)
+            .map(|i| if i % 16 < 8 { 0 } else { 255 })
+            .collect();
+        encoder
+            .encode(&pixels, 16, 8, jpeg_encoder::ColorType::Luma)
+            .unwrap();
+        let raster = decode("test.jpg", &bytes).unwrap();
+        assert_eq!((raster.width, raster.height), (16, 8));
+        assert!(raster.get(2, 4)[0] < 16);
+        assert!(raster.get(13, 4)[0] > 240);
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert!(matches!(
+            decode("test.txt", b"hello"),
+            Err(DrawingError::InvalidImage(_, _))
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+use std::sync::Arc;
+
+use crate::{error::DrawingError, renderer::positioning::place_rectangle};
+
+use super::grid::GridCell;
+use super::{
+    fits_file::{self, Frame},
+    image::{draw_raster, orient, Crop, Image},
+    image_cache::cached_fits,
+    positioning::{HorizontalAlignment, Length, VerticalAlignment},
+    raster::{Dither, Raster, Resample},
+    resources::resolve,
+    ColorFromTemplate, Point,
+};
+use embedded_graphics::prelude::{DrawTarget, PixelColor, Size};
+use embedded_graphics::primitives::Rectangle;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Stretch {
+    /// Straight line from the black to the white point
+    Linear,
+    /// Midtones transfer function, bringing the background to the target level
+    Midtones,
+    /// Inverse hyperbolic sine, bringing the background to the target level while keeping star cores
+    Asinh,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Fits {
+    /// FITS file, plain or tile compressed
+    pub path: String,
+    /// Index of the HDU. Default to the first one holding an image
+    pub hdu: Option<usize>,
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub align: Option<HorizontalAlignment>,
+    pub vertical_align: Option<VerticalAlignment>,
+    pub invert: Option<bool>,
+
+    /// Part of the frame to show, in its own pixels from the top left corner
+    pub region: Option<Crop>,
+    /// Box the frame is downsampled into, keeping its aspect ratio
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+
+    /// Default to midtones
+    pub stretch: Option<Stretch>,
+    /// Levels shown black and white, in data units. Default to the background
+    /// minus 2.8 deviations, and to the brightest pixel
+    pub black: Option<f64>,
+    pub white: Option<f64>,
+    /// Level of the background after a midtones or asinh stretch, from 0 to 1. Default to 0.25
+    pub target: Option<f64>,
+
+    /// Gray level from which pixels are white. Default to 128
+    pub threshold: Option<u8>,
+    /// Default to Floyd-Steinberg
+    pub dither: Option<Dither>,
+}
+
+// Median and normalized median absolute deviation, on a sample of the finite values
+fn statistics(data: &[f32]) -> Option<(f64, f64)> {
+    let step = (data.len() / 100_000).max(1);
+    let mut values: Vec<f32> = data
+        .iter()
+        .step_by(step)
+        .copied()
+        .filter(|v| v.is_finite())
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    let median = |values: &mut Vec<f32>| {
+        let middle = values.len() / 2;
+        *values.select_nth_unstable_by(middle, f32::total_cmp).1 as f64
+    };
+    let center = median(&mut values);
+    let mut deviations = values
+        .iter()
+        .map(|v| (*v as f64 - center).abs() as f32)
+        .collect();
+    Some((center, median(&mut deviations) * 1.4826))
+}
+
+// Midtones transfer function, mapping the balance to 0.5
+fn mtf(balance: f64, x: f64) -> f64 {
+    match x {
+        _ if x <= 0.0 => 0.0,
+        _ if x >= 1.0 => 1.0,
+        _ => (balance - 1.0) * x / ((2.0 * balance - 1.0) * x - balance),
+    }
+}
+
+// Strength of the asinh stretch that maps the level to the target, found by bisection
+fn asinh_strength(level: f64, target: f64) -> f64 {
+    let curve = |strength: f64| (strength * level).asinh() / strength.asinh();
+    if level <= 0.0 || level >= target {
+        return 1e-6;
+    }
+    let (mut low, mut high) = (-6.0f64, 9.0f64);
+    for _ in 0..60 {
+        let middle = (low + high) / 2.0;
+        match curve(10f64.powf(middle)) < target {
+            true => low = middle,
+            false => high = middle,
+        }
+    }
+    10f64.powf((low + high) / 2.0)
+}
+
+/// Gray levels of the frame, blank samples being transparent
+pub fn stretch(fits: &Fits, frame: &Frame) -> Raster {
+    let (median, deviation) = statistics(&frame.data).unwrap_or((0.0, 0.0));
+    let (min, max) = frame
+        .data
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(*v as f64), max.max(*v as f64))
+        });
+    let black = fits.black.unwrap_or((median - 2.8 * deviation).max(min));
+    let white = fits.white.unwrap_or(max);
+    let range = (white - black).max(f64::EPSILON);
+    let normalize = |v: f64| ((v - black) / range).clamp(0.0, 1.0);
+
+    let target = fits.target.unwrap_or(0.25).clamp(0.0, 1.0);
+    let background = normalize(median);
+    let curve: Box<dyn Fn(f64) -> f64> = match fits.stretch.unwrap_or(Stretch::Midtones) {
+        Stretch::Linear => Box::new(|x| x),
+        Stretch::Midtones => {
+            let balance = mtf(target, background);
+            Box::new(move |x| mtf(balance, x))
+        }
+        Stretch::Asinh => {
+            let strength = asinh_strength(background, target);
+            Box::new(move |x| (strength * x).asinh() / strength.asinh())
+        }
+    };
+
+    Raster::from_fn(frame.width, frame.height, |x, y| {
+        let value = frame.data[(y * frame.width + x) as usize];
+        match value.is_finite() {
+            true => [(curve(normalize(value as f64)) * 255.0).round() as u8, 255],
+            false => [0, 0],
+        }
+    })
+}
+
+// Stretched and downsampled frame, from the cache when possible
+fn load(fits: &Fits) -> Result<Arc<Raster>, DrawingError> {
+    let file = resolve(&fits.path)?;
+    cached_fits(fits, &file, || {
+        let bytes =
+            std::fs::read(&file).map_err(|e| DrawingError::ResourceError(fits.path.clone(), e))?;
+        let frame = fits_file::read(&bytes, fits.hdu)
+            .map_err(|e| DrawingError::InvalidImage(fits.path.clone(), e))?;
+        let frame = match &fits.region {
+            Some(region) => frame.crop(region.x, region.y, region.width, region.height),
+            None => frame,
+        };
+        let scaling = Image {
+            width: fits.width,
+            height: fits.height,
+            resample: Some(Resample::Area),
+            ..Default::default()
+        };
+        Ok(orient(&scaling, stretch(fits, &frame)))
+    })
+}
+
+/// Area covered by the frame
+pub fn fits_bounds(fits: &Fits) -> Rectangle {
+    let size = match load(fits) {
+        Ok(raster) => Size::new(raster.width, raster.height),
+        Err(e) => {
+            println!("Unable to read the size of {}: {:?}", fits.path, e);
+            Size::zero()
+        }
+    };
+    let origin = place_rectangle(size, fits.align, fits.vertical_align, fits.position.clone());
+    Rectangle::new(origin.into(), size)
+}
+
+pub fn draw_fits<D, TargetColor>(display: &mut D, fits: &Fits) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: Into<DrawingError>>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let raster = load(fits)?;
+    let origin = place_rectangle(
+        Size::new(raster.width, raster.height),
+        fits.align,
+        fits.vertical_align,
+        fits.position.clone(),
+    );
+    draw_raster(
+        display,
+        &raster,
+        origin.into(),
+        raster.binarize(
+            fits.threshold.unwrap_or(128),
+            fits.dither.unwrap_or(Dither::FloydSteinberg),
+        ),
+        fits.invert.unwrap_or(false),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::fits_file::tests::header;
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+
+    #[test]
+    fn test_stretch() {
+        assert_eq!(mtf(0.5, 0.3), 0.3);
+        assert!((mtf(mtf(0.25, 0.1), 0.1) - 0.25).abs() < 1e-9);
+        let strength = asinh_strength(0.1, 0.25);
+        assert!(((strength * 0.1).asinh() / strength.asinh() - 0.25).abs() < 1e-6);
+
+        // Flat background with a bright pixel
+        let mut data = vec![100.0; 16];
+        data[5] = 1000.0;
+        let frame = Frame {
+            width: 4,
+            height: 4,
+            data,
+        };
+        let gray = |fits: &Fits| {
+            let raster = stretch(fits, &frame);
+            (raster.get(0, 0)[0], raster.get(1, 1)[0])
+        };
+        assert_eq!(gray(&Fits::default()), (0, 255));
+        let levels = Fits {
+            stretch: Some(Stretch::Linear),
+            black: Some(0.0),
+            white: Some(2000.0),
+            ..Default::default()
+        };
+        assert_eq!(gray(&levels), (13, 128));
+        let midtones = Fits {
+            black: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(gray(&midtones).0, 64);
+        let asinh = Fits {
+            stretch: Some(Stretch::Asinh),
+            black: Some(0.0),
+            ..Default::default()
+        };
+        assert_eq!(gray(&asinh).0, 64);
+    }
+
+    #[test]
+    fn test_render() {
+        // A 32x16 sky, brighter to the right, with a star. Rows are stored from the bottom
+        let (width, height) = (32, 16);
+        let mut file = header(&[
+            ("SIMPLE", "T"),
+            ("BITPIX", "16"),
+            ("NAXIS", "2"),
+            ("NAXIS1", &width.to_string()),
+            ("NAXIS2", &height.to_string()),
+            ("BZERO", "32768"),
+        ]);
+        for y in (0..height).rev() {
+            for x in 0..width {
+                let distance = ((x - 24) * (x - 24) + (y - 6) * (y - 6)) as f64;
+                let value = 1000.0 + 20.0 * x as f64 + 30000.0 * (-distance / 4.0).exp();
+                file.extend(((value - 32768.0) as i16).to_be_bytes());
+            }
+        }
+        file.resize(file.len().div_ceil(2880) * 2880, 0);
+        // Resources are read relative to the working directory
+        let path = std::path::PathBuf::from(format!("target/test-{}.fits", std::process::id()));
+        std::fs::write(&path, file).unwrap();
+
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 48,
+                height: 16,
+            },
+            vec![
+                Primitive::Fits(Fits {
+                    path: path.to_string_lossy().to_string(),
+                    ..Default::default()
+                }),
+                Primitive::Fits(Fits {
+                    path: path.to_string_lossy().to_string(),
+                    position: Point {
+                        x: 32,
+                        ..Default::default()
+                    },
+                    region: Some(Crop {
+                        x: 16,
+                        y: 0,
+                        width: 16,
+                        height: 16,
+                    }),
+                    width: Some(8.into()),
+                    stretch: Some(Stretch::Linear),
+                    dither: Some(Dither::None),
+                    ..Default::default()
+                }),
+            ],
+            None,
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+          ▄  ▄ ▄ ▄ ▄▀ ▄ ▀▄ ▀▄ ▀▄        ████████
+       ▀  ▄ ▄ ▄ ▄ ▄ ▄▀▄█▄█▄▀ █ ▄   ▄█   ████████
+     ▀  ▄  ▄  ▄ ▄  ▀▄▀████▀█▄ ▀▄        ████████
+      ▄  ▄  ▄ ▄  ▀ ▀▄███████ █ ▄        ████████
+         ▄  ▄ ▄ ▀ ▀▄ █▄█▀█▀▄▀ ▀▄████████████████
+     ▀ ▄  ▄  ▄  ▀ ▀ ▄  ▀▄▀ ▀▄▀▄ ████████████████
+        ▄  ▄  ▀ ▀ ▀▄ ▀ ▀ ▀▄▀▄ ▀▄████████████████
+     ▀   ▄   ▀ ▀ ▀▄ ▄▀ █ ▀▄ ▄▀▄ ████████████████
+"#
+        );
+    }
+}
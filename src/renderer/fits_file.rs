@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+const BLOCK: usize = 2880;
+const CARD: usize = 80;
+/// Largest frame read, so that bad headers cannot exhaust the memory
+const MAX_PIXELS: usize = 1 << 26;
+
+// Number of pixels of a frame, checked against overflows and the limit
+fn pixel_count(width: u32, height: u32) -> Result<usize, String> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .filter(|pixels| *pixels <= MAX_PIXELS)
+        .ok_or_else(|| "image too large".to_string())
+}
+
+/// Samples of an image HDU, row by row, in physical units. Blank samples are NaN
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<f32>,
+}
+
+impl Frame {
+    /// Reverse the order of the rows
+    fn flip(self) -> Frame {
+        let data = self
+            .data
+            .chunks(self.width.max(1) as usize)
+            .rev()
+            .flatten()
+            .copied()
+            .collect();
+        Frame { data, ..self }
+    }
+
+    /// Part of the frame inside the rectangle
+    pub fn crop(&self, left: u32, top: u32, width: u32, height: u32) -> Frame {
+        let (left, top) = (left.min(self.width), top.min(self.height));
+        let width = width.min(self.width.saturating_sub(left));
+        let height = height.min(self.height.saturating_sub(top));
+        let data = (top..top + height)
+            .flat_map(|y| {
+                let start = y as usize * self.width as usize + left as usize;
+                self.data[start..start + width as usize].iter().copied()
+            })
+            .collect();
+        Frame {
+            width,
+            height,
+            data,
+        }
+    }
+}
+
+// Values of the keywords, without quotes or comments
+struct Header(HashMap<String, String>);
+
+impl Header {
+    /// Header and the length of its blocks
+    fn parse(bytes: &[u8]) -> Result<(Header, usize), String> {
+        let mut cards = HashMap::new();
+        for (i, card) in bytes.chunks(CARD).enumerate() {
+            if card.len() < CARD {
+                break;
+            }
+            let keyword = String::from_utf8_lossy(&card[..8]).trim().to_string();
+            if keyword == "END" {
+                return Ok((Header(cards), ((i + 1) * CARD).div_ceil(BLOCK) * BLOCK));
+            }
+            if &card[8..10] == b"= " {
+                cards.insert(keyword, value(&String::from_utf8_lossy(&card[10..])));
+            }
+        }
+        Err("truncated header".to_string())
+    }
+
+    fn string(&self, keyword: &str) -> Option<&str> {
+        self.0.get(keyword).map(|s| s.as_str())
+    }
+
+    fn float(&self, keyword: &str) -> Option<f64> {
+        self.string(keyword)
+            .and_then(|s| s.replace('D', "E").parse().ok())
+    }
+
+    fn int(&self, keyword: &str) -> Option<i64> {
+        self.float(keyword).map(|f| f as i64)
+    }
+
+    fn require(&self, keyword: &str) -> Result<i64, String> {
+        self.int(keyword)
+            .ok_or_else(|| format!("missing {} keyword", keyword))
+    }
+
+    // Non negative size given by a required keyword
+    fn size(&self, keyword: &str) -> Result<u32, String> {
+        let value = self.require(keyword)?;
+        u32::try_from(value).map_err(|_| format!("invalid {} {}", keyword, value))
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.string("ZIMAGE") == Some("T")
+    }
+
+    fn has_image(&self) -> bool {
+        self.is_compressed()
+            || (matches!(self.string("XTENSION"), None | Some("IMAGE"))
+                && self.int("NAXIS").unwrap_or(0) >= 2
+                && self.int("NAXIS1").unwrap_or(0) > 0
+                && self.int("NAXIS2").unwrap_or(0) > 0)
+    }
+
+    fn data_length(&self) -> Result<usize, String> {
+        let invalid = || "invalid data size".to_string();
+        let axes = self.int("NAXIS").unwrap_or(0);
+        if axes <= 0 {
+            return Ok(0);
+        }
+        let mut count: u64 = 1;
+        for i in 1..=axes {
+            let axis = u64::try_from(self.int(&format!("NAXIS{}", i)).unwrap_or(0))
+                .map_err(|_| invalid())?;
+            count = count.checked_mul(axis).ok_or_else(invalid)?;
+        }
+        let bytes = self.int("BITPIX").unwrap_or(8).unsigned_abs() / 8;
+        let groups = u64::try_from(self.int("GCOUNT").unwrap_or(1)).map_err(|_| invalid())?;
+        let heap = u64::try_from(self.int("PCOUNT").unwrap_or(0)).map_err(|_| invalid())?;
+        heap.checked_add(count)
+            .and_then(|count| count.checked_mul(groups))
+            .and_then(|count| count.checked_mul(bytes))
+            .and_then(|length| usize::try_from(length).ok())
+            .ok_or_else(invalid)
+    }
+}
+
+fn value(text: &str) -> String {
+    let text = text.trim_start();
+    match text.strip_prefix('\'') {
+        // Quotes are doubled inside strings
+        Some(rest) => {
+            let mut value = String::new();
+            let mut chars = rest.chars().peekable();
+            while let Some(c) = chars.next() {
+                if c != '\'' {
+                    value.push(c);
+                } else if chars.peek() == Some(&'\'') {
+                    chars.next();
+                    value.push(c);
+                } else {
+                    break;
+                }
+            }
+            value.trim_end().to_string()
+        }
+        None => text
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+/// Image of the given HDU, or of the first one holding an image
+pub fn read(bytes: &[u8], hdu: Option<usize>) -> Result<Frame, String> {
+    let mut start = 0;
+    let mut index = 0;
+    while start < bytes.len() {
+        let (header, length) = Header::parse(&bytes[start..])?;
+        let data_start = start + length;
+        let data_length = header.data_length()?;
+        let data = data_start
+            .checked_add(data_length)
+            .and_then(|end| bytes.get(data_start..end))
+            .ok_or_else(|| "truncated data".to_string())?;
+
+        let wanted = match hdu {
+            Some(hdu) => hdu == index,
+            None => header.has_image(),
+        };
+        if wanted {
+            let frame = match header.is_compressed() {
+                true => read_compressed(&header, data)?,
+                false => read_image(&header, data)?,
+            };
+            // The first row is at the bottom, unless told otherwise
+            return Ok(match header.string("ROWORDER") {
+                Some("TOP-DOWN") => frame,
+                _ => frame.flip(),
+            });
+        }
+        start = data_start + data_length.div_ceil(BLOCK) * BLOCK;
+        index += 1;
+    }
+    Err(match hdu {
+        Some(hdu) => format!("no HDU {}", hdu),
+        None => "no image HDU".to_string(),
+    })
+}
+
+// Big endian sample of the given BITPIX
+fn sample(bitpix: i64, bytes: &[u8]) -> f64 {
+    match bitpix {
+        8 => bytes[0] as f64,
+        16 => i16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+        32 => i32::from_be_bytes(bytes[..4].try_into().unwrap()) as f64,
+        64 => i64::from_be_bytes(bytes[..8].try_into().unwrap()) as f64,
+        -32 => f32::from_be_bytes(bytes[..4].try_into().unwrap()) as f64,
+        _ => f64::from_be_bytes(bytes[..8].try_into().unwrap()),
+    }
+}
+
+fn sample_size(bitpix: i64) -> Result<usize, String> {
+    match bitpix {
+        8 | 16 | 32 | 64 | -32 | -64 => Ok(bitpix.unsigned_abs() as usize / 8),
+        _ => Err(format!("invalid BITPIX {}", bitpix)),
+    }
+}
+
+// Plain image. Only the first plane of cubes is read
+fn read_image(header: &Header, data: &[u8]) -> Result<Frame, String> {
+    let width = header.size("NAXIS1")?;
+    let height = header.size("NAXIS2")?;
+    let bitpix = header.require("BITPIX")?;
+    let size = sample_size(bitpix)?;
+    let (scale, zero) = (
+        header.float("BSCALE").unwrap_or(1.0),
+        header.float("BZERO").unwrap_or(0.0),
+    );
+    let blank = header.float("BLANK");
+
+    let count = pixel_count(width, height)?;
+    if data.len() < count * size {
+        return Err("truncated data".to_string());
+    }
+    let data = data
+        .chunks_exact(size)
+        .take(count)
+        .map(|bytes| {
+            let raw = sample(bitpix, bytes);
+            match bitpix > 0 && Some(raw) == blank {
+                true => f32::NAN,
+                false => (raw * scale + zero) as f32,
+            }
+        })
+        .collect();
+    Ok(Frame {
+        width,
+        height,
+        data,
+    })
+}
+
+// Offset in the row and type of a table column, with the sizes of the columns before it
+fn column(header: &Header, name: &str) -> Option<(usize, char)> {
+    let mut offset = 0;
+    for i in 1..=header.int("TFIELDS")? {
+        let form = header.string(&format!("TFORM{}", i))?;
+        let digits = form.chars().take_while(|c| c.is_ascii_digit()).count();
+        let repeat: usize = form[..digits].parse().unwrap_or(1);
+        let code = form[digits..].chars().next()?;
+        if header.string(&format!("TTYPE{}", i)) == Some(name) {
+            return Some((offset, code));
+        }
+        let width = match code {
+            'X' => Some(repeat.div_ceil(8)),
+            'L' | 'B' | 'A' => Some(repeat),
+            'I' => repeat.checked_mul(2),
+            'J' | 'E' => repeat.checked_mul(4),
+            'K' | 'D' | 'C' | 'P' => repeat.checked_mul(8),
+            'M' | 'Q' => repeat.checked_mul(16),
+            _ => None,
+        };
+        offset = width.and_then(|width| offset.checked_add(width))?;
+    }
+    None
+}
+
+// Per tile value from a table column, or else a keyword
+fn tile_value(header: &Header, row: &[u8], name: &str) -> Option<f64> {
+    match column(header, name) {
+        Some((offset, 'D')) => row.get(offset..offset + 8).map(|bytes| sample(-64, bytes)),
+        Some((offset, 'E')) => row.get(offset..offset + 4).map(|bytes| sample(-32, bytes)),
+        _ => header.float(name),
+    }
+}
+
+// Tile compressed image, stored in a binary table with one tile per row
+fn read_compressed(header: &Header, data: &[u8]) -> Result<Frame, String> {
+    let (width, height) = (header.size("ZNAXIS1")?, header.size("ZNAXIS2")?);
+    let pixels = pixel_count(width, height)?;
+    let (width, height) = (width as usize, height as usize);
+    let bitpix = header.require("ZBITPIX")?;
+    let tile_width = header
+        .int("ZTILE1")
+        .unwrap_or(width as i64)
+        .clamp(1, width.max(1) as i64) as usize;
+    let tile_height = header
+        .int("ZTILE2")
+        .unwrap_or(1)
+        .clamp(1, height.max(1) as i64) as usize;
+    let compression = header.string("ZCMPTYPE").unwrap_or_default();
+
+    let truncated = |what: &str| format!("truncated {}", what);
+    let row_length = header.size("NAXIS1")? as usize;
+    let rows = header.size("NAXIS2")? as usize;
+    let table = row_length
+        .checked_mul(rows)
+        .and_then(|length| data.get(..length))
+        .ok_or_else(|| truncated("table"))?;
+    let heap = match header.int("THEAP") {
+        Some(heap) => usize::try_from(heap).map_err(|_| format!("invalid THEAP {}", heap))?,
+        None => table.len(),
+    };
+    let (descriptor, kind) = column(header, "COMPRESSED_DATA")
+        .ok_or_else(|| "missing COMPRESSED_DATA column".to_string())?;
+
+    // Compression parameters, as ZNAMEi / ZVALi pairs
+    let parameter = |name: &str| {
+        (1..10)
+            .find(|i| header.string(&format!("ZNAME{}", i)) == Some(name))
+            .and_then(|i| header.int(&format!("ZVAL{}", i)))
+    };
+    let block_size = parameter("BLOCKSIZE").unwrap_or(32).clamp(1, 1 << 16) as usize;
+    let byte_pix = parameter("BYTEPIX").unwrap_or(match bitpix {
+        8 => 1,
+        16 => 2,
+        _ => 4,
+    });
+    let byte_pix =
+        usize::try_from(byte_pix).map_err(|_| format!("invalid BYTEPIX {}", byte_pix))?;
+    let (scale, zero) = (
+        header.float("BSCALE").unwrap_or(1.0),
+        header.float("BZERO").unwrap_or(0.0),
+    );
+    let blank = header.float("ZBLANK");
+
+    let mut frame = vec![f32::NAN; pixels];
+    let tiles_x = width.div_ceil(tile_width).max(1);
+    for (i, row) in table.chunks_exact(row_length.max(1)).enumerate() {
+        // Byte count and heap offset of the tile
+        let field = |index: usize| -> Option<usize> {
+            let size = if kind == 'Q' { 8 } else { 4 };
+            let bytes = row.get(descriptor + index * size..descriptor + (index + 1) * size)?;
+            match kind {
+                'Q' => usize::try_from(u64::from_be_bytes(bytes.try_into().ok()?)).ok(),
+                _ => usize::try_from(u32::from_be_bytes(bytes.try_into().ok()?)).ok(),
+            }
+        };
+        let (count, offset) = field(0)
+            .zip(field(1))
+            .ok_or_else(|| "invalid tile descriptor".to_string())?;
+        let bytes = heap
+            .checked_add(offset)
+            .and_then(|start| Some(start..start.checked_add(count)?))
+            .and_then(|range| data.get(range))
+            .ok_or_else(|| truncated("heap"))?;
+
+        let (left, top) = ((i % tiles_x) * tile_width, (i / tiles_x) * tile_height);
+        let tile_width = tile_width.min(width.saturating_sub(left));
+        let tile_height = tile_height.min(height.saturating_sub(top));
+        let pixels = tile_width * tile_height;
+
+        // Floating point images are quantized to integers, and restored with a scale and zero.
+        // The dithering noise added by the quantization is below a level and ignored
+        let quantized = tile_value(header, row, "ZSCALE").zip(tile_value(header, row, "ZZERO"));
+        let values = match compression {
+            "RICE_1" => rice(bytes, pixels, block_size, byte_pix)?
+                .into_iter()
+                .map(|v| v as f64)
+                .collect(),
+            "GZIP_1" => {
+                let mut unzipped = Vec::new();
+                flate2::read::GzDecoder::new(bytes)
+                    .read_to_end(&mut unzipped)
+                    .map_err(|e| e.to_string())?;
+                let stored = if quantized.is_some() { 32 } else { bitpix };
+                let size = sample_size(stored)?;
+                if unzipped.len() < pixels * size {
+                    return Err("truncated tile".to_string());
+                }
+                unzipped
+                    .chunks_exact(size)
+                    .map(|bytes| sample(stored, bytes))
+                    .collect::<Vec<_>>()
+            }
+            _ => return Err(format!("unsupported compression {}", compression)),
+        };
+
+        let (scale, zero) = quantized.unwrap_or((scale, zero));
+        for (j, raw) in values.into_iter().take(pixels).enumerate() {
+            let value = match Some(raw) == blank {
+                true => f32::NAN,
+                false => (raw * scale + zero) as f32,
+            };
+            frame[(top + j / tile_width) * width + left + j % tile_width] = value;
+        }
+    }
+    Ok(Frame {
+        width: width as u32,
+        height: height as u32,
+        data: frame,
+    })
+}
+
+struct Bits<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Bits<'_> {
+    fn read(&mut self, count: u32) -> Result<u64, String> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self
+                .bytes
+                .get(self.position / 8)
+                .ok_or_else(|| "truncated tile".to_string())?;
+            value = (value << 1) | ((byte >> (7 - self.position % 8)) & 1) as u64;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    // Length of a run of zeros, and the one ending it
+    fn zeros(&mut self) -> Result<u64, String> {
+        let mut count = 0;
+        while self.read(1)? == 0 {
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Rice decompression, as written by cfitsio
+fn rice(
+    bytes: &[u8],
+    count: usize,
+    block_size: usize,
+    byte_pix: usize,
+) -> Result<Vec<i64>, String> {
+    let (fs_bits, fs_max, sample_bits) = match byte_pix {
+        1 => (3, 6, 8),
+        2 => (4, 14, 16),
+        4 => (5, 25, 32),
+        _ => return Err(format!("invalid BYTEPIX {}", byte_pix)),
+    };
+    // Differences wrap around in the integer type of the samples
+    let wrap = |value: i64| match byte_pix {
+        1 => value as u8 as i64,
+        2 => value as i16 as i64,
+        _ => value as i32 as i64,
+    };
+
+    let mut bits = Bits { bytes, position: 0 };
+    let mut last = wrap(bits.read(sample_bits)? as i64);
+    let mut values = Vec::with_capacity(count);
+    while values.len() < count {
+        let fs = bits.read(fs_bits)? as i64 - 1;
+        for _ in 0..block_size.min(count - values.len()) {
+            let difference = match fs {
+                // Low entropy block, all the differences are zero
+                _ if fs < 0 => 0,
+                // High entropy block, the differences are stored as they are
+                _ if fs == fs_max => bits.read(sample_bits)?,
+                _ => (bits.zeros()? << fs) | bits.read(fs as u32)?,
+            };
+            let difference = match difference & 1 {
+                1 => -((difference >> 1) as i64) - 1,
+                _ => (difference >> 1) as i64,
+            };
+            last = wrap(last + difference);
+            values.push(last);
+        }
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// FITS header made of the given cards, padded to whole blocks
+    pub fn header(cards: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (keyword, value) in cards {
+            bytes.extend(format!("{:<8}= {:<70}", keyword, value).bytes());
+        }
+        bytes.extend(format!("{:<80}", "END").bytes());
+        bytes.resize(bytes.len().div_ceil(BLOCK) * BLOCK, b' ');
+        bytes
+    }
+
+    fn pad(mut bytes: Vec<u8>) -> Vec<u8> {
+        bytes.resize(bytes.len().div_ceil(BLOCK) * BLOCK, 0);
+        bytes
+    }
+
+    #[test]
+    fn test_header() {
+        assert_eq!(value(" 'It''s'   / comment"), "It's");
+        assert_eq!(value("  16 / bits"), "16");
+        let (header, length) =
+            Header::parse(&header(&[("SIMPLE", "T"), ("BZERO", "3.2768D4")])).unwrap();
+        assert_eq!(length, BLOCK);
+        assert_eq!(header.float("BZERO"), Some(32768.0));
+        assert!(Header::parse(b"SIMPLE  = T").is_err());
+    }
+
+    #[test]
+    fn test_read_image() {
+        // An empty primary HDU, then a 16 bit unsigned image extension
+        let mut file = header(&[("SIMPLE", "T"), ("BITPIX", "8"), ("NAXIS", "0")]);
+        file.extend(header(&[
+            ("XTENSION", "'IMAGE   '"),
+            ("BITPIX", "16"),
+            ("NAXIS", "2"),
+            ("NAXIS1", "3"),
+            ("NAXIS2", "2"),
+            ("BZERO", "32768"),
+            ("BLANK", "-32768"),
+        ]));
+        let samples: [i16; 6] = [-32768, -32767, 0, 1, 32767, -32768];
+        file.extend(pad(samples.iter().flat_map(|s| s.to_be_bytes()).collect()));
+
+        // Rows are stored from the bottom
+        let frame = read(&file, None).unwrap();
+        assert_eq!((frame.width, frame.height), (3, 2));
+        assert_eq!(frame.data[..2], [32769.0, 65535.0]);
+        assert!(frame.data[2].is_nan() && frame.data[3].is_nan());
+        assert_eq!(frame.data[4..], [1.0, 32768.0]);
+        assert_eq!(frame.crop(1, 1, 4, 4).data, vec![1.0, 32768.0]);
+        assert!(read(&file, Some(0)).is_err());
+        assert_eq!(read(&file, Some(1)).unwrap().data[4..], [1.0, 32768.0]);
+        assert!(read(&file, Some(2)).is_err());
+        assert!(frame.crop(u32::MAX, 0, 2, 2).data.is_empty());
+
+        // Bad sizes are errors
+        let image = |width: &str, height: &str| {
+            let mut file = header(&[
+                ("SIMPLE", "T"),
+                ("BITPIX", "16"),
+                ("NAXIS", "2"),
+                ("NAXIS1", width),
+                ("NAXIS2", height),
+            ]);
+            file.extend(pad(vec![0; 12]));
+            read(&file, None)
+        };
+        assert_eq!(image("3", "-2"), Err("invalid data size".to_string()));
+        assert_eq!(
+            image("4294967296", "4294967296"),
+            Err("invalid data size".to_string())
+        );
+        let (negative, _) = Header::parse(&header(&[("NAXIS1", "-3")])).unwrap();
+        assert!(negative.size("NAXIS1").is_err());
+        assert_eq!(
+            pixel_count(100_000, 100_000),
+            Err("image too large".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rice() {
+        // 10, then differences of 0, 1, 0 and -2 with fs = 0
+        assert_eq!(
+            rice(&[0, 10, 25, 136], 4, 32, 2).unwrap(),
+            vec![10, 11, 11, 9]
+        );
+        assert!(rice(&[0, 10, 25], 4, 32, 2).is_err());
+    }
+
+    #[test]
+    fn test_read_compressed() {
+        // A 3x2 image in tiles of a row, compressed with gzip
+        let tiles: Vec<Vec<u8>> = [[1i16, 2, 3], [4, 5, 6]]
+            .iter()
+            .map(|row| {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(&row.iter().flat_map(|s| s.to_be_bytes()).collect::<Vec<_>>())
+                    .unwrap();
+                encoder.finish().unwrap()
+            })
+            .collect();
+
+        let mut table = Vec::new();
+        let mut heap: Vec<u8> = Vec::new();
+        for tile in &tiles {
+            table.extend((tile.len() as u32).to_be_bytes());
+            table.extend((heap.len() as u32).to_be_bytes());
+            heap.extend(tile);
+        }
+        let heap_length = heap.len().to_string();
+        let mut file = header(&[("SIMPLE", "T"), ("BITPIX", "8"), ("NAXIS", "0")]);
+        file.extend(header(&[
+            ("XTENSION", "'BINTABLE'"),
+            ("BITPIX", "8"),
+            ("NAXIS", "2"),
+            ("NAXIS1", "8"),
+            ("NAXIS2", "2"),
+            ("PCOUNT", &heap_length),
+            ("TFIELDS", "1"),
+            ("TTYPE1", "'COMPRESSED_DATA'"),
+            ("TFORM1", "'1PB(40)'"),
+            ("ZIMAGE", "T"),
+            ("ZCMPTYPE", "'GZIP_1  '"),
+            ("ZBITPIX", "16"),
+            ("ZNAXIS", "2"),
+            ("ZNAXIS1", "3"),
+            ("ZNAXIS2", "2"),
+            ("ZTILE1", "3"),
+            ("ZTILE2", "1"),
+            ("BSCALE", "2"),
+        ]));
+        table.extend(heap);
+        file.extend(pad(table));
+
+        let frame = read(&file, None).unwrap();
+        assert_eq!(frame.data, vec![8.0, 10.0, 12.0, 2.0, 4.0, 6.0]);
+
+        // Rows too short for the descriptors of the tiles
+        let card = file.windows(12).position(|w| w == b"NAXIS1  = 8 ").unwrap();
+        file[card + 10] = b'4';
+        assert_eq!(
+            read(&file, None),
+            Err("invalid tile descriptor".to_string())
+        );
+    }
+}
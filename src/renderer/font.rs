@@ -0,0 +1,256 @@
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::text::Alignment;
+
+type Font = &'static MonoFont<'static>;
+
+/// Names of the fonts available in every ISO 8859 charset
+pub const FONT_NAMES: [&str; 22] = [
+    "4X6",
+    "5X7",
+    "5X8",
+    "6X9",
+    "6X10",
+    "6X12",
+    "6X13",
+    "6X13_BOLD",
+    "6X13_ITALIC",
+    "7X13",
+    "7X13_BOLD",
+    "7X13_ITALIC",
+    "7X14",
+    "7X14_BOLD",
+    "8X13",
+    "8X13_BOLD",
+    "8X13_ITALIC",
+    "9X15",
+    "9X15_BOLD",
+    "9X18",
+    "9X18_BOLD",
+    "10X20",
+];
+
+// Lookup of the font named `$name` inside the embedded_graphics::mono_font::$charset module
+macro_rules! charset_font {
+    ($charset:ident, $name:expr) => {{
+        use embedded_graphics::mono_font::$charset::*;
+        let font: Option<Font> = match $name {
+            "4X6" => Some(&FONT_4X6),
+            "5X7" => Some(&FONT_5X7),
+            "5X8" => Some(&FONT_5X8),
+            "6X9" => Some(&FONT_6X9),
+            "6X10" => Some(&FONT_6X10),
+            "6X12" => Some(&FONT_6X12),
+            "6X13" => Some(&FONT_6X13),
+            "6X13_BOLD" => Some(&FONT_6X13_BOLD),
+            "6X13_ITALIC" => Some(&FONT_6X13_ITALIC),
+            "7X13" => Some(&FONT_7X13),
+            "7X13_BOLD" => Some(&FONT_7X13_BOLD),
+            "7X13_ITALIC" => Some(&FONT_7X13_ITALIC),
+            "7X14" => Some(&FONT_7X14),
+            "7X14_BOLD" => Some(&FONT_7X14_BOLD),
+            "8X13" => Some(&FONT_8X13),
+            "8X13_BOLD" => Some(&FONT_8X13_BOLD),
+            "8X13_ITALIC" => Some(&FONT_8X13_ITALIC),
+            "9X15" => Some(&FONT_9X15),
+            "9X15_BOLD" => Some(&FONT_9X15_BOLD),
+            "9X18" => Some(&FONT_9X18),
+            "9X18_BOLD" => Some(&FONT_9X18_BOLD),
+            "10X20" => Some(&FONT_10X20),
+            _ => None,
+        };
+        font
+    }};
+}
+
+fn jis_x0201_font(name: &str) -> Option<Font> {
+    use embedded_graphics::mono_font::jis_x0201::*;
+    match name {
+        "6X13" => Some(&FONT_6X13),
+        "7X14" => Some(&FONT_7X14),
+        "8X13" => Some(&FONT_8X13),
+        "9X15" => Some(&FONT_9X15),
+        "9X18" => Some(&FONT_9X18),
+        "10X20" => Some(&FONT_10X20),
+        _ => None,
+    }
+}
+
+/// Resolve a single font specification: `NAME` or `CHARSET:NAME`.
+/// Without charset, ISO 8859-1 is used (it is a superset of ASCII, with °, ±, µ, accents...)
+pub fn resolve_font(spec: &str) -> Option<Font> {
+    let spec = spec.trim().to_ascii_uppercase();
+    let (charset, name) = match spec.split_once(':') {
+        Some((charset, name)) => (charset.trim(), name.trim()),
+        None => ("LATIN1", spec.as_str()),
+    };
+
+    match charset {
+        "ASCII" => charset_font!(ascii, name),
+        "LATIN1" | "ISO_8859_1" => charset_font!(iso_8859_1, name),
+        "LATIN2" | "ISO_8859_2" => charset_font!(iso_8859_2, name),
+        "LATIN3" | "ISO_8859_3" => charset_font!(iso_8859_3, name),
+        "LATIN4" | "ISO_8859_4" => charset_font!(iso_8859_4, name),
+        "CYRILLIC" | "ISO_8859_5" => charset_font!(iso_8859_5, name),
+        "GREEK" | "ISO_8859_7" => charset_font!(iso_8859_7, name),
+        "LATIN5" | "ISO_8859_9" => charset_font!(iso_8859_9, name),
+        "LATIN6" | "ISO_8859_10" => charset_font!(iso_8859_10, name),
+        "LATIN7" | "ISO_8859_13" => charset_font!(iso_8859_13, name),
+        "LATIN8" | "ISO_8859_14" => charset_font!(iso_8859_14, name),
+        "LATIN9" | "ISO_8859_15" => charset_font!(iso_8859_15, name),
+        "LATIN10" | "ISO_8859_16" => charset_font!(iso_8859_16, name),
+        "JIS_X0201" => jis_x0201_font(name),
+        _ => None,
+    }
+}
+
+fn default_font() -> Font {
+    &embedded_graphics::mono_font::iso_8859_1::FONT_6X10
+}
+
+// No font maps this one: its index is the replacement glyph
+const UNMAPPED_CHAR: char = '\u{FFFF}';
+
+fn has_glyph(font: Font, c: char) -> bool {
+    c == '?' || font.glyph_mapping.index(c) != font.glyph_mapping.index(UNMAPPED_CHAR)
+}
+
+/// Ordered list of fonts. Each glyph is taken from the first font that provides it.
+#[derive(Debug, Clone)]
+pub struct FontChain {
+    fonts: Vec<Font>,
+}
+
+impl FontChain {
+    /// Parse a comma separated list of font specifications (see resolve_font).
+    /// Unknown fonts are ignored; the chain falls back to 6X10 when empty
+    pub fn parse(font: &Option<String>) -> FontChain {
+        let mut fonts = Vec::new();
+        if let Some(font) = font {
+            for spec in font.split(',') {
+                match resolve_font(spec) {
+                    Some(f) => fonts.push(f),
+                    None => println!("Invalid font : {:?}", spec),
+                }
+            }
+        }
+        if fonts.is_empty() {
+            fonts.push(default_font());
+        }
+        FontChain { fonts }
+    }
+
+    pub fn primary(&self) -> Font {
+        self.fonts[0]
+    }
+
+    pub fn font_for(&self, c: char) -> Font {
+        self.fonts
+            .iter()
+            .find(|f| has_glyph(f, c))
+            .copied()
+            .unwrap_or(self.primary())
+    }
+
+    /// Split a single line into runs of consecutive chars rendered with the same font
+    pub fn runs<'a>(&self, line: &'a str) -> Vec<(Font, &'a str)> {
+        let mut runs: Vec<(Font, &'a str)> = Vec::new();
+        let mut start = 0;
+        let mut current: Option<Font> = None;
+        for (i, c) in line.char_indices() {
+            let font = self.font_for(c);
+            if let Some(cur) = current {
+                if !std::ptr::eq(cur, font) {
+                    runs.push((cur, &line[start..i]));
+                    start = i;
+                }
+            }
+            current = Some(font);
+        }
+        if let Some(cur) = current {
+            runs.push((cur, &line[start..]));
+        }
+        runs
+    }
+
+    /// Width in pixels of a single line
+    pub fn line_width(&self, line: &str) -> u32 {
+        let mut width = 0;
+        let mut spacing = 0;
+        for c in line.chars() {
+            let font = self.font_for(c);
+            width += font.character_size.width + font.character_spacing;
+            spacing = font.character_spacing;
+        }
+        width.saturating_sub(spacing)
+    }
+
+    /// Distance from the start of the text to where the following text starts
+    pub fn advance(&self, text: &str) -> u32 {
+        text.chars()
+            .map(|c| {
+                let font = self.font_for(c);
+                font.character_size.width + font.character_spacing
+            })
+            .sum()
+    }
+
+    pub fn line_height(&self) -> u32 {
+        self.primary().character_size.height
+    }
+
+    pub fn baseline(&self) -> u32 {
+        self.primary().baseline
+    }
+
+    /// Horizontal start of a line of the given width, following embedded-graphics conventions
+    pub fn align_line(x: i32, width: u32, align: Alignment) -> i32 {
+        match align {
+            Alignment::Left => x,
+            Alignment::Center => x - (width as i32 - 1) / 2,
+            Alignment::Right => x - (width as i32 - 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_graphics::prelude::Size;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_font() {
+        let font = resolve_font("4x6").unwrap();
+        assert_eq!(font.character_size, Size::new(4, 6));
+        assert!(has_glyph(font, '°'));
+
+        let font = resolve_font("ascii:9x18_bold").unwrap();
+        assert_eq!(font.character_size, Size::new(9, 18));
+        assert!(!has_glyph(font, '°'));
+
+        let font = resolve_font("latin9 : 6X10").unwrap();
+        assert!(has_glyph(font, '€'));
+
+        for name in FONT_NAMES {
+            assert!(resolve_font(&format!("latin2:{}", name)).is_some());
+        }
+        assert!(resolve_font("jis_x0201:4X6").is_none());
+        assert!(resolve_font("12X12").is_none());
+    }
+
+    #[test]
+    fn test_fallback() {
+        let chain = FontChain::parse(&Some("ascii:6X10, latin9:6X10".to_string()));
+        let runs = chain.runs("-5°C 12€");
+        assert_eq!(
+            runs.iter().map(|r| r.1).collect::<Vec<_>>(),
+            vec!["-5", "°", "C 12", "€"]
+        );
+        assert!(has_glyph(runs[3].0, '€'));
+        assert_eq!(chain.line_width("-5°C"), 24);
+
+        // Missing everywhere: rendered with the primary font
+        let chain = FontChain::parse(&Some("ascii:6X10".to_string()));
+        assert!(std::ptr::eq(chain.font_for('°'), chain.primary()));
+    }
+}
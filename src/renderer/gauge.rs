@@ -0,0 +1,340 @@
+use crate::error::DrawingError;
+
+use embedded_graphics::primitives::{Circle, Line, PrimitiveStyle};
+use embedded_graphics::text::Alignment;
+use embedded_graphics::Drawable;
+
+use super::drawing_error::IntoDrawingError;
+use super::grid::GridCell;
+use super::positioning::{place_rectangle, HorizontalAlignment, Length, VerticalAlignment};
+use super::series::format_value;
+use super::text::{draw_text, TextItem};
+use super::{ColorFromTemplate, Point};
+use embedded_graphics::prelude::{DrawTarget, PixelColor, PointsIter, Primitive};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GaugeMode {
+    /// Fill the arc from min up to the value
+    Arc,
+    /// Point a needle at the value
+    Needle,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Zone {
+    pub from: f64,
+    pub to: f64,
+    /// Hatched with this color on two colors panels
+    pub color: Option<String>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Gauge {
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub diameter: Length,
+
+    pub align: Option<HorizontalAlignment>,
+    pub vertical_align: Option<VerticalAlignment>,
+
+    pub value: f64,
+    /// Range of the gauge. Default to 0 and 1
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+
+    /// Arc (default) or needle
+    pub mode: Option<GaugeMode>,
+
+    /// Angles in degrees, clockwise from 3 o'clock. Default to 135 and 270
+    pub start_angle: Option<f64>,
+    pub sweep_angle: Option<f64>,
+
+    /// Width of the arc. Default to a fifth of the diameter
+    pub thickness: Option<u32>,
+
+    /// Number of tick marks, spread from min to max
+    pub ticks: Option<u32>,
+    /// Print min and max at the ends of the arc
+    pub labels: Option<bool>,
+    pub font: Option<String>,
+
+    pub zones: Option<Vec<Zone>>,
+
+    pub color: Option<String>,
+}
+
+pub fn draw_gauge<D, TargetColor>(display: &mut D, gauge: &Gauge) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let color = TargetColor::resolve(&gauge.color);
+    let min = gauge.min.unwrap_or(0.0);
+    let max = gauge.max.unwrap_or(1.0);
+    let start = gauge.start_angle.unwrap_or(135.0);
+    let sweep = gauge.sweep_angle.unwrap_or(270.0).clamp(0.0, 360.0);
+    let thickness = gauge
+        .thickness
+        .unwrap_or(gauge.diameter.pixels() / 5)
+        .max(1) as f64;
+
+    let fraction = |v: f64| -> f64 {
+        if max > min {
+            ((v - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+
+    let origin = place_rectangle(
+        embedded_graphics::geometry::Size {
+            width: gauge.diameter.pixels(),
+            height: gauge.diameter.pixels(),
+        },
+        gauge.align,
+        gauge.vertical_align,
+        gauge.position.clone(),
+    );
+    let center_x = origin.x as f64 + (gauge.diameter.pixels() as f64 - 1.0) / 2.0;
+    let center_y = origin.y as f64 + (gauge.diameter.pixels() as f64 - 1.0) / 2.0;
+    let outer = gauge.diameter.pixels() as f64 / 2.0;
+    let inner = outer - thickness;
+
+    // Point at a distance from the center, at the angle of a fraction of the range
+    let polar = |fraction: f64, distance: f64| {
+        let angle = (start + fraction * sweep).to_radians();
+        embedded_graphics::prelude::Point::new(
+            (center_x + distance * angle.cos()).round() as i32,
+            (center_y + distance * angle.sin()).round() as i32,
+        )
+    };
+
+    let mut pixels = Vec::new();
+
+    // Walk the bounding square, and classify pixels by radius and angle
+    let arc_fill = matches!(gauge.mode, None | Some(GaugeMode::Arc));
+    let value = fraction(gauge.value);
+    let zones: Vec<(f64, f64, TargetColor)> = gauge
+        .zones
+        .iter()
+        .flatten()
+        .map(|z| {
+            (
+                fraction(z.from),
+                fraction(z.to),
+                TargetColor::resolve(&z.color),
+            )
+        })
+        .collect();
+    for y in 0..gauge.diameter.pixels() as i32 {
+        for x in 0..gauge.diameter.pixels() as i32 {
+            let px = origin.x + x;
+            let py = origin.y + y;
+            let dx = px as f64 - center_x;
+            let dy = py as f64 - center_y;
+            let distance = (dx * dx + dy * dy).sqrt();
+            if distance > outer - 0.5 || distance <= inner - 0.5 {
+                continue;
+            }
+            let angle = dy.atan2(dx).to_degrees();
+            let relative = (angle - start).rem_euclid(360.0);
+            if relative > sweep {
+                continue;
+            }
+            let at = if sweep > 0.0 { relative / sweep } else { 0.0 };
+            let point = embedded_graphics::prelude::Point::new(px, py);
+
+            let edge = distance > outer - 1.5 || distance <= inner + 0.5;
+            if edge || (arc_fill && at <= value) {
+                pixels.push(embedded_graphics::Pixel(point, color));
+            } else if let Some((_, _, zone_color)) =
+                zones.iter().find(|(from, to, _)| at >= *from && at <= *to)
+            {
+                if !TargetColor::is_binary() || (px + py) % 3 == 0 {
+                    pixels.push(embedded_graphics::Pixel(point, *zone_color));
+                }
+            }
+        }
+    }
+
+    // Close the ends of the arc
+    if sweep < 360.0 {
+        for end in [0.0, 1.0] {
+            pixels.extend(
+                Line::new(polar(end, inner), polar(end, outer - 1.0))
+                    .points()
+                    .map(|p| embedded_graphics::Pixel(p, color)),
+            );
+        }
+    }
+
+    let ticks = gauge.ticks.unwrap_or(0);
+    for i in 0..ticks {
+        let at = if ticks > 1 {
+            i as f64 / (ticks - 1) as f64
+        } else {
+            0.0
+        };
+        pixels.extend(
+            Line::new(polar(at, inner - 3.0), polar(at, inner - 1.0))
+                .points()
+                .map(|p| embedded_graphics::Pixel(p, color)),
+        );
+    }
+
+    display
+        .draw_iter(pixels)
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    if matches!(gauge.mode, Some(GaugeMode::Needle)) {
+        let center = embedded_graphics::prelude::Point::new(
+            center_x.round() as i32,
+            center_y.round() as i32,
+        );
+        Line::new(center, polar(value, inner - 1.0))
+            .into_styled(PrimitiveStyle::with_stroke(color, 1))
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+        Circle::with_center(center, 5)
+            .into_styled(PrimitiveStyle::with_fill(color))
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+    }
+
+    if gauge.labels.unwrap_or(false) {
+        // Centered below the ends of the arc
+        for (at, value) in [(0.0, min), (1.0, max)] {
+            let end = polar(at, outer - thickness / 2.0);
+            draw_text(
+                display,
+                &TextItem {
+                    value: format_value(value),
+                    position: Point {
+                        x: end.x,
+                        y: end.y + (thickness / 2.0).ceil() as i32 + 1,
+                        ..Default::default()
+                    },
+                    font: gauge.font.clone(),
+                    color: gauge.color.clone(),
+                    align: Some(Alignment::Center),
+                    ..Default::default()
+                },
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+
+    #[test]
+    fn test_arc_with_zone() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 32,
+            },
+            vec![Primitive::Gauge(Gauge {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                diameter: 32.into(),
+                value: 0.4,
+                thickness: Some(6),
+                ticks: Some(5),
+                zones: Some(vec![Zone {
+                    from: 0.8,
+                    to: 1.0,
+                    color: Some("0".to_string()),
+                }]),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+███████████▀▀▀▀▀▀▀▀▀▀███████████
+███████▀  ▄██████████▄▄ ▀███████
+█████▀    ▀██████████████▄▀█████
+███▀       ▀▄▄▄ ▄▄▄▄▀▀█████▄▀███
+██       ▄█████ ██████▄▀████▄ ██
+█▀      ████████████████ ████▄▀█
+█      ▄▀▀████████████▀▀▄ ████ █
+█      ██████████████████ █▀▄█ █
+█      ██████████████████ ▄█▀▄ █
+█      ██████████████████ ▀▄█▀ █
+█▄      ██▀▄████████▄▀██ ▄█▀▄▀▄█
+██       ▄████████████▄ █▀▄█▀ ██
+███▄   ▄████████████████▄▀▀ ▄███
+█████▄████████████████████▄█████
+████████████████████████████████
+████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_needle_with_labels() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 40,
+                height: 28,
+            },
+            vec![Primitive::Gauge(Gauge {
+                position: Point {
+                    x: 4,
+                    y: 2,
+                    ..Default::default()
+                },
+                diameter: 32.into(),
+                value: 75.0,
+                min: Some(0.0),
+                max: Some(100.0),
+                mode: Some(GaugeMode::Needle),
+                start_angle: Some(180.0),
+                sweep_angle: Some(180.0),
+                thickness: Some(3),
+                labels: Some(true),
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████████████████████████████
+███████████████▀▀▀▀▀▀▀▀▀▀███████████████
+███████████▀ ▄▄▀▀▀▀▀▀▀▀▀▀▄▄ ▀███████████
+█████████▀▄█▀▄▄██████████▄▄▀█▄▀█████████
+███████▀▄█ ▄████████████████  █▄▀███████
+██████ ▄▀▄████████████████▀▄██▄▀▄ ██████
+█████▀▄▀▄███████████████▀▄█████▄▀▄▀█████
+█████ █ ███████████████▀▄███████ █ █████
+█████ █ ██████████▀    █████████ ▀ ▀████
+█████▄▄▄██████████     █████████████████
+█████▀█████████████▄▄▄███████▀ ██▀▄▀█▀▄▀
+████ ▀ ███████████████████████ ██ ▄ █ ▄ 
+████▄▀▄██████████████████████▄▄▄██▄███▄█
+████████████████████████████████████████
+"#
+        );
+    }
+}
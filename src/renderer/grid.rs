@@ -0,0 +1,509 @@
+use std::str::FromStr;
+
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::Rectangle;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::container::Container;
+use super::layout::ItemAlignment;
+use super::Primitive;
+
+/// Size of a grid row or column
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Track {
+    /// Pixels: 24 or "24px"
+    Fixed(u32),
+    /// Share of the remaining space: "1fr"
+    Fraction(u32),
+    /// Largest child of the track: "auto"
+    Auto,
+}
+
+impl FromStr for Track {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("Invalid track size: {:?}", s);
+        if s == "auto" {
+            Ok(Track::Auto)
+        } else if let Some(fraction) = s.strip_suffix("fr") {
+            fraction
+                .trim()
+                .parse()
+                .map(Track::Fraction)
+                .map_err(|_| invalid())
+        } else {
+            s.strip_suffix("px")
+                .unwrap_or(s)
+                .trim()
+                .parse()
+                .map(Track::Fixed)
+                .map_err(|_| invalid())
+        }
+    }
+}
+
+impl Serialize for Track {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Track::Fixed(pixels) => pixels.serialize(s),
+            Track::Fraction(fraction) => format!("{}fr", fraction).serialize(s),
+            Track::Auto => "auto".serialize(s),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Track {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum TrackValue {
+            Pixels(u32),
+            Text(String),
+        }
+
+        match TrackValue::deserialize(d)? {
+            TrackValue::Pixels(pixels) => Ok(Track::Fixed(pixels)),
+            TrackValue::Text(text) => text.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+/// Cell of a child in a grid parent. Indices start at 0
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GridCell {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: Option<usize>,
+    pub column_span: Option<usize>,
+}
+
+/// Rows a grid may have, so that a cell far down does not take all the memory
+const MAX_ROWS: usize = 256;
+
+// (row, column, row span, column span)
+type Placement = (usize, usize, usize, usize);
+
+fn mark(occupied: &mut Vec<Vec<bool>>, columns: usize, (row, column, rows, cols): Placement) {
+    let end = row.saturating_add(rows).min(MAX_ROWS);
+    if occupied.len() < end {
+        occupied.resize(end, vec![false; columns]);
+    }
+    for line in occupied.iter_mut().skip(row).take(rows) {
+        for cell in line.iter_mut().skip(column).take(cols) {
+            *cell = true;
+        }
+    }
+}
+
+// Cell asked by a child
+fn cell(primitive: &Primitive) -> Option<&GridCell> {
+    match primitive {
+        Primitive::Dummy(_) => None,
+        Primitive::Text(text) => text.cell.as_ref(),
+        Primitive::RichText(rich) => rich.cell.as_ref(),
+        Primitive::QRCode(qr) => qr.cell.as_ref(),
+        Primitive::Barcode(barcode) => barcode.cell.as_ref(),
+        Primitive::Image(image) => image.cell.as_ref(),
+        Primitive::Fits(fits) => fits.cell.as_ref(),
+        Primitive::Icon(icon) => icon.cell.as_ref(),
+        Primitive::Progress(progress) => progress.cell.as_ref(),
+        Primitive::Chart(chart) => chart.cell.as_ref(),
+        Primitive::Bars(bars) => bars.cell.as_ref(),
+        Primitive::Gauge(gauge) => gauge.cell.as_ref(),
+        Primitive::Clock(clock) => clock.cell.as_ref(),
+        Primitive::Table(table) => table.cell.as_ref(),
+        Primitive::Container(container) => container.cell.as_ref(),
+    }
+}
+
+// Children with a cell are placed first, then the others fill the free cells row by row
+fn place_cells(children: &[&Primitive], columns: usize) -> Vec<Placement> {
+    let mut occupied: Vec<Vec<bool>> = Vec::new();
+
+    let explicit: Vec<Option<Placement>> = children
+        .iter()
+        .map(|child| {
+            let cell = cell(child)?;
+            let row = cell.row.min(MAX_ROWS - 1);
+            let column = cell.column.min(columns - 1);
+            let placement = (
+                row,
+                column,
+                cell.row_span.unwrap_or(1).clamp(1, MAX_ROWS - row),
+                cell.column_span.unwrap_or(1).clamp(1, columns - column),
+            );
+            mark(&mut occupied, columns, placement);
+            Some(placement)
+        })
+        .collect();
+
+    let mut next = 0;
+    explicit
+        .into_iter()
+        .map(|placement| {
+            placement.unwrap_or_else(|| {
+                while occupied
+                    .get(next / columns)
+                    .map(|line| line[next % columns])
+                    .unwrap_or(false)
+                {
+                    next += 1;
+                }
+                let placement = (next / columns, next % columns, 1, 1);
+                mark(&mut occupied, columns, placement);
+                placement
+            })
+        })
+        .collect()
+}
+
+// Size of each track. Without available space, fractions are sized like auto tracks
+fn size_tracks(
+    specs: &[Track],
+    count: usize,
+    available: Option<u32>,
+    spacing: u32,
+    items: &[(usize, usize, u32)],
+) -> Vec<u32> {
+    let spec = |i: usize| specs.get(i).copied().unwrap_or(Track::Auto);
+    let content = |i: usize| {
+        items
+            .iter()
+            .filter(|(start, span, _)| *start == i && *span == 1)
+            .map(|(_, _, size)| *size)
+            .max()
+            .unwrap_or(0)
+    };
+
+    let mut sizes: Vec<u32> = (0..count)
+        .map(|i| match (spec(i), available) {
+            (Track::Fixed(pixels), _) => pixels,
+            (Track::Fraction(_), Some(_)) => 0,
+            (Track::Fraction(_), None) | (Track::Auto, _) => content(i),
+        })
+        .collect();
+
+    if let Some(available) = available {
+        let used = sizes.iter().sum::<u32>() + spacing * (count as u32).saturating_sub(1);
+        let free = available.saturating_sub(used);
+        let fractions: Vec<(usize, u32)> = (0..count)
+            .filter_map(|i| match spec(i) {
+                Track::Fraction(f) => Some((i, f)),
+                _ => None,
+            })
+            .collect();
+        let total: u32 = fractions.iter().map(|(_, f)| f).sum();
+        // The last fraction takes what rounding left
+        let mut given = 0;
+        for (n, (i, f)) in fractions.iter().enumerate() {
+            let share = if n + 1 == fractions.len() {
+                free - given
+            } else {
+                (free * f).checked_div(total).unwrap_or(0)
+            };
+            sizes[*i] = share;
+            given += share;
+        }
+    }
+    sizes
+}
+
+struct Tracks {
+    placements: Vec<Placement>,
+    rows: Vec<u32>,
+    columns: Vec<u32>,
+    spacing: u32,
+}
+
+impl Tracks {
+    fn new(
+        container: &Container,
+        area: Option<Size>,
+        children: &[&Primitive],
+        bounds: &[Rectangle],
+    ) -> Tracks {
+        let column_specs = container
+            .columns
+            .clone()
+            .unwrap_or_else(|| vec![Track::Fraction(1)]);
+        let row_specs = container.rows.clone().unwrap_or_default();
+        let columns = column_specs.len().max(1);
+        let placements = place_cells(children, columns);
+        let rows = placements
+            .iter()
+            .map(|(row, _, span, _)| row + span)
+            .max()
+            .unwrap_or(0)
+            .max(row_specs.len());
+        let spacing = container.spacing.unwrap_or(0);
+
+        let items = |vertical: bool| -> Vec<(usize, usize, u32)> {
+            placements
+                .iter()
+                .zip(bounds.iter())
+                .map(|((row, column, rows, cols), b)| {
+                    if vertical {
+                        (*row, *rows, b.size.height)
+                    } else {
+                        (*column, *cols, b.size.width)
+                    }
+                })
+                .collect()
+        };
+
+        Tracks {
+            columns: size_tracks(
+                &column_specs,
+                columns,
+                area.map(|a| a.width),
+                spacing,
+                &items(false),
+            ),
+            rows: size_tracks(
+                &row_specs,
+                rows,
+                area.map(|a| a.height),
+                spacing,
+                &items(true),
+            ),
+            placements,
+            spacing,
+        }
+    }
+
+    fn total(&self, sizes: &[u32]) -> u32 {
+        sizes.iter().sum::<u32>() + self.spacing * (sizes.len() as u32).saturating_sub(1)
+    }
+
+    // (offset, length) of a span of tracks
+    fn span(&self, sizes: &[u32], start: usize, span: usize) -> (i32, u32) {
+        let offset = sizes[..start].iter().sum::<u32>() + self.spacing * start as u32;
+        let length = sizes[start..start + span].iter().sum::<u32>()
+            + self.spacing * (span as u32).saturating_sub(1);
+        (offset as i32, length)
+    }
+}
+
+/// Size of the content of a grid container, from the natural size of its children
+pub fn grid_size(container: &Container, children: &[&Primitive], bounds: &[Rectangle]) -> Size {
+    let tracks = Tracks::new(container, None, children, bounds);
+    Size::new(tracks.total(&tracks.columns), tracks.total(&tracks.rows))
+}
+
+/// Slots of the children of a grid container, in an area of the given size
+pub fn grid_slots(
+    container: &Container,
+    area: Size,
+    children: &[&Primitive],
+    bounds: &[Rectangle],
+) -> Vec<Rectangle> {
+    let align = container.align_items.unwrap_or(ItemAlignment::Start);
+    let tracks = Tracks::new(container, Some(area), children, bounds);
+
+    tracks
+        .placements
+        .iter()
+        .zip(bounds.iter())
+        .map(|((row, column, rows, cols), b)| {
+            let (x, width) = tracks.span(&tracks.columns, *column, *cols);
+            let (y, height) = tracks.span(&tracks.rows, *row, *rows);
+            let (dx, width) = super::layout::place(align, width, b.size.width);
+            let (dy, height) = super::layout::place(align, height, b.size.height);
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(x + dx, y + dy),
+                Size::new(width, height),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_merge_keys::serde_yaml;
+
+    #[test]
+    fn test_track_parsing() {
+        assert_eq!("auto".parse(), Ok(Track::Auto));
+        assert_eq!("2fr".parse(), Ok(Track::Fraction(2)));
+        assert_eq!("24px".parse(), Ok(Track::Fixed(24)));
+        assert_eq!("24".parse(), Ok(Track::Fixed(24)));
+        assert!("big".parse::<Track>().is_err());
+
+        let tracks: Vec<Track> = yaml_merge_keys::serde_yaml::from_str("[12, 1fr, auto]").unwrap();
+        assert_eq!(
+            tracks,
+            vec![Track::Fixed(12), Track::Fraction(1), Track::Auto]
+        );
+    }
+
+    #[test]
+    fn test_size_tracks() {
+        let specs = [
+            Track::Fixed(10),
+            Track::Fraction(1),
+            Track::Auto,
+            Track::Fraction(2),
+        ];
+        let items = [(2, 1, 7), (2, 1, 5), (0, 2, 50)];
+        assert_eq!(
+            size_tracks(&specs, 4, Some(60), 1, &items),
+            vec![10, 13, 7, 27]
+        );
+        assert_eq!(size_tracks(&specs, 4, None, 1, &items), vec![10, 0, 7, 0]);
+    }
+
+    #[test]
+    fn test_render() {
+        use super::super::container::Container;
+        use super::super::layout::{ItemAlignment, Justify, Layout};
+        use super::super::tests::render;
+        use super::super::text::TextItem;
+        use super::super::Size;
+
+        let text = |value: &str| {
+            Primitive::Text(TextItem {
+                value: value.to_string(),
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })
+        };
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 48,
+                height: 20,
+            },
+            vec![Primitive::Container(Container {
+                size: Some(Size {
+                    width: 48,
+                    height: 20,
+                    ..Default::default()
+                }),
+                layout: Some(Layout::Grid),
+                columns: Some(vec![Track::Auto, Track::Fraction(1)]),
+                rows: Some(vec![Track::Auto, Track::Fraction(1)]),
+                spacing: Some(2),
+                align_items: Some(ItemAlignment::Stretch),
+                content: Some(vec![
+                    Primitive::Container(Container {
+                        cell: Some(GridCell {
+                            row: 1,
+                            column: 0,
+                            row_span: None,
+                            column_span: Some(2),
+                        }),
+                        layout: Some(Layout::Row),
+                        justify: Some(Justify::Center),
+                        content: Some(vec![text("bottom")]),
+                        ..Default::default()
+                    }),
+                    text("12:00"),
+                    Primitive::Container(Container {
+                        layout: Some(Layout::Row),
+                        justify: Some(Justify::End),
+                        content: Some(vec![text("W")]),
+                        ..Default::default()
+                    }),
+                ]),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+▀ ██▀▄▀██▀██▀▄▀█▀▄▀█████████████████████████ █ █
+█ ███▀▄█████ ▄ █ ▄ █████████████████████████   █
+▄▄▄█▄▄▄██▄███▄███▄██████████████████████████▄█▄█
+████████████████████████████████████████████████
+████████████ ▀███▀██▀ ▀█▀ ▀██▀██▀█▀█████████████
+████████████ █ █ █ ██ ███ ██ █ █ ▄ █████████████
+████████████▄▄███▄████▄███▄██▄██▄█▄█████████████
+████████████████████████████████████████████████
+████████████████████████████████████████████████
+████████████████████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_cell() {
+        // Any child may take a cell, the others fill the free ones in order
+        let template = super::super::parse(
+            serde_yaml::from_str(
+                r#"
+        - container:
+            position: {x: 0, y: 0}
+            size: {width: 32, height: 14}
+            layout: grid
+            columns: [1fr, 1fr]
+            spacing: 2
+            style: {font: 4x6, color: "0"}
+            content:
+              - !text {value: "A", position: {x: 0, y: 0}}
+              - !text
+                value: "B"
+                position: {x: 0, y: 0}
+                cell: {row: 0, column: 0}
+              - !text {value: "C", position: {x: 0, y: 0}}
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let display = super::super::tests::render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 14,
+            },
+            template,
+            None,
+        );
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+ ▄▀██████████████▀▄▀████████████
+ ▄▀██████████████ ▄ ████████████
+▄▄███████████████▄█▄████████████
+████████████████████████████████
+▀▄▀█████████████████████████████
+ █▀█████████████████████████████
+█▄██████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_far_cell() {
+        // Cells far down are kept in a bounded grid
+        let text = |cell: Option<GridCell>| {
+            Primitive::Text(super::super::text::TextItem {
+                value: "A".to_string(),
+                cell,
+                ..Default::default()
+            })
+        };
+        let far = text(Some(GridCell {
+            row: usize::MAX,
+            column: 0,
+            row_span: Some(usize::MAX),
+            column_span: None,
+        }));
+        let free = text(None);
+        let placements = place_cells(&[&far, &free], 2);
+        assert_eq!(placements, vec![(MAX_ROWS - 1, 0, 1, 1), (0, 0, 1, 1)]);
+    }
+}
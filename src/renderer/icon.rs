@@ -0,0 +1,330 @@
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::{error::DrawingError, renderer::positioning::place_rectangle};
+
+use super::grid::GridCell;
+use super::{
+    image::{load, load_file, Image},
+    positioning::{HorizontalAlignment, VerticalAlignment},
+    raster::Raster,
+    ColorFromTemplate, Point,
+};
+use embedded_graphics::prelude::{DrawTarget, PixelColor, Size};
+use serde::{Deserialize, Serialize};
+
+/// Built-in icons, as the content of a 24x24 SVG document
+const ICONS: &[(&str, &str)] = &[
+    (
+        "wifi-1",
+        r##"<path d="M12 21q-1.05 0-1.775-.725T9.5 18.5t.725-1.775T12 16t1.775.725t.725 1.775t-.725 1.775T12 21"/>"##,
+    ),
+    (
+        "wifi-2",
+        r##"<path d="M12 21q-1.05 0-1.775-.725T9.5 18.5t.725-1.775T12 16t1.775.725t.725 1.775t-.725 1.775T12 21m-5.65-5.65l-2.1-2.15q1.475-1.475 3.463-2.337T12 10t4.288.875t3.462 2.375l-2.1 2.1q-1.1-1.1-2.55-1.725T12 13t-3.1.625t-2.55 1.725"/>"##,
+    ),
+    (
+        "wifi-3",
+        r##"<path d="M12 21q-1.05 0-1.775-.725T9.5 18.5t.725-1.775T12 16t1.775.725t.725 1.775t-.725 1.775T12 21m0-11q1.875 0 3.563.6t3.062 1.65q.5.375.513.988T18.7 14.3q-.425.425-1.05.438t-1.125-.338q-.95-.65-2.1-1.025T12 13t-2.425.375t-2.1 1.025q-.5.35-1.125.325t-1.05-.45q-.425-.45-.425-1.062t.5-.988q1.375-1.05 3.063-1.638T12 10m0-6q3.125 0 5.888 1.025t4.962 2.9q.5.425.525 1.05t-.425 1.075q-.425.425-1.05.438t-1.125-.388q-1.8-1.475-4.037-2.287T12 7t-4.737.813T3.225 10.1q-.5.4-1.125.388t-1.05-.438Q.6 9.6.625 8.975t.525-1.05q2.2-1.875 4.963-2.9T12 4"/>"##,
+    ),
+    (
+        "wifi-off",
+        r##"<path d="m19.75 22.6l-9.4-9.45q-1.175.275-2.187.825T6.35 15.35l-2.1-2.15q.8-.8 1.725-1.4t1.975-1.05L5.7 8.5q-1.025.525-1.913 1.163T2.1 11.1L0 8.95q.8-.8 1.663-1.437T3.5 6.3L1.4 4.2l1.4-1.4l18.4 18.4zM12 21q-1.05 0-1.775-.737T9.5 18.5q0-1.05.725-1.775T12 16t1.775.725t.725 1.775q0 1.025-.725 1.763T12 21m5.9-5.95l-.725-.725l-.725-.725l-3.6-3.6q2.025.2 3.787 1.025T19.75 13.2zm4-3.95q-1.925-1.925-4.462-3.012T12 7q-.525 0-1.012.038T10 7.15L7.45 4.6q1.1-.3 2.238-.45T12 4q3.55 0 6.625 1.325T24 8.95z"/>"##,
+    ),
+    (
+        "access-point",
+        r##"<path d="M4.93 4.93A9.97 9.97 0 0 0 2 12c0 2.76 1.12 5.26 2.93 7.07l1.41-1.41A7.94 7.94 0 0 1 4 12c0-2.21.89-4.22 2.34-5.66zm14.14 0l-1.41 1.41A7.96 7.96 0 0 1 20 12c0 2.22-.89 4.22-2.34 5.66l1.41 1.41A9.97 9.97 0 0 0 22 12c0-2.76-1.12-5.26-2.93-7.07M7.76 7.76A5.98 5.98 0 0 0 6 12c0 1.65.67 3.15 1.76 4.24l1.41-1.41A4 4 0 0 1 8 12c0-1.11.45-2.11 1.17-2.83zm8.48 0l-1.41 1.41A4 4 0 0 1 16 12c0 1.11-.45 2.11-1.17 2.83l1.41 1.41A5.98 5.98 0 0 0 18 12c0-1.65-.67-3.15-1.76-4.24M12 10a2 2 0 0 0-2 2a2 2 0 0 0 2 2a2 2 0 0 0 2-2a2 2 0 0 0-2-2"/>"##,
+    ),
+    (
+        "ethernet",
+        r##"<path d="M7 15h2v3h2v-3h2v3h2v-3h2v3h2V9h-4V6H9v3H5v9h2zM4.38 3h15.25A2.37 2.37 0 0 1 22 5.38v14.25A2.37 2.37 0 0 1 19.63 22H4.38A2.37 2.37 0 0 1 2 19.63V5.38C2 4.06 3.06 3 4.38 3"/>"##,
+    ),
+    (
+        "ethernet-off",
+        r##"<path d="M2.39 1.73L1.11 3l1.21 1.21c-.2.35-.32.74-.32 1.17v14.25A2.37 2.37 0 0 0 4.38 22h15.25c.15 0 .29 0 .44-.04l.77.77l1.27-1.27zM15 18h-2v-3h-2v3H9v-3H7v3H5V9h2.11L15 16.89zM9.2 6l-3-3h13.43A2.37 2.37 0 0 1 22 5.38V18.8l-3-3V9h-4V6z"/>"##,
+    ),
+    (
+        "bluetooth",
+        r##"<path d="M6 7l11 10-5 5V2l5 5L6 17" stroke="#000" stroke-width="2" fill="none" stroke-linejoin="round"/>"##,
+    ),
+    (
+        "battery-0",
+        r##"<path fill-rule="evenodd" d="M4 7h14a2 2 0 0 1 2 2v6a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2zm0 2v6h14V9zm16 1h2v4h-2z"/>"##,
+    ),
+    (
+        "battery-1",
+        r##"<path fill-rule="evenodd" d="M4 7h14a2 2 0 0 1 2 2v6a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2zm0 2v6h14V9zm16 1h2v4h-2z"/><path d="M5 10h3v4H5z"/>"##,
+    ),
+    (
+        "battery-2",
+        r##"<path fill-rule="evenodd" d="M4 7h14a2 2 0 0 1 2 2v6a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2zm0 2v6h14V9zm16 1h2v4h-2z"/><path d="M5 10h6v4H5z"/>"##,
+    ),
+    (
+        "battery-3",
+        r##"<path fill-rule="evenodd" d="M4 7h14a2 2 0 0 1 2 2v6a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2zm0 2v6h14V9zm16 1h2v4h-2z"/><path d="M5 10h9v4H5z"/>"##,
+    ),
+    (
+        "battery-4",
+        r##"<path fill-rule="evenodd" d="M4 7h14a2 2 0 0 1 2 2v6a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2zm0 2v6h14V9zm16 1h2v4h-2z"/><path d="M5 10h12v4H5z"/>"##,
+    ),
+    (
+        "thermometer",
+        r##"<path d="M10 14.5V5a2 2 0 0 1 4 0v9.5a4 4 0 1 1-4 0z" stroke="#000" stroke-width="2" fill="none"/><circle cx="12" cy="18" r="2"/><path d="M11 9h2v8h-2z"/>"##,
+    ),
+    (
+        "mount",
+        r##"<path d="M2.5 11l15-6.5 2 4.5-15 6.5zM17 3.5l3.5-1.5 2.5 5.5-3.5 1.5z"/><path d="M11 13l-4 9m4-9l4 9m-4-9v9" stroke="#000" stroke-width="2" fill="none" stroke-linecap="round"/>"##,
+    ),
+    (
+        "camera",
+        r##"<path fill-rule="evenodd" d="M4 7h3l2-3h6l2 3h3a2 2 0 0 1 2 2v10a2 2 0 0 1-2 2H4a2 2 0 0 1-2-2V9a2 2 0 0 1 2-2zm8 2.5a4 4 0 1 0 0 8a4 4 0 1 0 0-8z"/><circle cx="12" cy="13.5" r="2"/>"##,
+    ),
+    (
+        "focuser",
+        r##"<path d="M3 8V3h5m8 0h5v5m0 8v5h-5m-8 0H3v-5" stroke="#000" stroke-width="2" fill="none"/><circle cx="12" cy="12" r="3"/>"##,
+    ),
+    (
+        "guider",
+        r##"<circle cx="12" cy="12" r="7" stroke="#000" stroke-width="2" fill="none"/><path d="M12 1v7m0 8v7M1 12h7m8 0h7" stroke="#000" stroke-width="2" fill="none"/>"##,
+    ),
+    (
+        "filter-wheel",
+        r##"<path fill-rule="evenodd" d="M12 2a10 10 0 1 0 0 20a10 10 0 1 0 0-20zm0 2.5a2.5 2.5 0 1 0 0 5a2.5 2.5 0 1 0 0-5zm0 10a2.5 2.5 0 1 0 0 5a2.5 2.5 0 1 0 0-5zm-5-5a2.5 2.5 0 1 0 0 5a2.5 2.5 0 1 0 0-5zm10 0a2.5 2.5 0 1 0 0 5a2.5 2.5 0 1 0 0-5z"/>"##,
+    ),
+    (
+        "clock",
+        r##"<circle cx="12" cy="12" r="9" stroke="#000" stroke-width="2" fill="none"/><path d="M12 6v6l4 3" stroke="#000" stroke-width="2" fill="none"/>"##,
+    ),
+    (
+        "moon",
+        r##"<path d="M14 2a10 10 0 1 0 8 14A8 8 0 0 1 14 2z"/>"##,
+    ),
+    (
+        "warning",
+        r##"<path fill-rule="evenodd" d="M12 2L1 21h22zm-1 7h2v6h-2zm0 8h2v2h-2z"/>"##,
+    ),
+    (
+        "check",
+        r##"<path d="M4 12.5l5 5L20 6.5" stroke="#000" stroke-width="3" fill="none"/>"##,
+    ),
+    (
+        "cross",
+        r##"<path d="M5 5l14 14M19 5L5 19" stroke="#000" stroke-width="3"/>"##,
+    ),
+];
+
+/// Extensions looked up in the user icon directories
+const EXTENSIONS: &[&str] = &["svg", "png", "gif", "bmp", "pbm"];
+
+static ICON_DIRS: Lazy<Mutex<Vec<PathBuf>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Directories searched for icons before the built-in ones, the first ones first
+pub fn set_icon_dirs(dirs: Vec<PathBuf>) {
+    *ICON_DIRS.lock().unwrap() = dirs;
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Icon {
+    pub name: String,
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    /// Width and height of the icon. Default to 24
+    pub size: Option<u32>,
+    pub align: Option<HorizontalAlignment>,
+    pub vertical_align: Option<VerticalAlignment>,
+    pub color: Option<String>,
+}
+
+pub fn icon_size(icon: &Icon) -> u32 {
+    icon.size.unwrap_or(24)
+}
+
+// Image of the icon, from the user directories or else built in
+fn icon_image(icon: &Icon) -> Result<Image, DrawingError> {
+    let size = icon_size(icon);
+    let image = Image {
+        width: Some(size.into()),
+        height: Some(size.into()),
+        ..Default::default()
+    };
+    // Names must not reach outside the directories
+    if icon.name.is_empty() || icon.name.contains(['/', '\\']) || icon.name.starts_with('.') {
+        return Err(DrawingError::UnknownIcon(icon.name.clone()));
+    }
+    for dir in ICON_DIRS.lock().unwrap().iter() {
+        for extension in EXTENSIONS {
+            let path = dir.join(format!("{}.{}", icon.name, extension));
+            if path.is_file() {
+                return Ok(Image {
+                    path: path.to_string_lossy().to_string(),
+                    ..image
+                });
+            }
+        }
+    }
+    match ICONS.iter().find(|(name, _)| *name == icon.name) {
+        Some((_, content)) => Ok(Image {
+            svg: Some(format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="24" height="24" viewBox="0 0 24 24">{}</svg>"#,
+                content
+            )),
+            ..image
+        }),
+        None => Err(DrawingError::UnknownIcon(icon.name.clone())),
+    }
+}
+
+// Icons of the user directories are read without checking their path
+fn load_icon(icon: &Icon) -> Result<Arc<Raster>, DrawingError> {
+    let image = icon_image(icon)?;
+    match image.svg {
+        Some(_) => load(&image),
+        None => load_file(&image, Path::new(&image.path)),
+    }
+}
+
+/// Paint the dark opaque pixels of the icon in its color
+pub fn draw_icon<D, TargetColor>(display: &mut D, icon: &Icon) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: Into<DrawingError>>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let raster = load_icon(icon)?;
+    let color = TargetColor::resolve(&icon.color);
+    let size = icon_size(icon);
+    let origin = place_rectangle(
+        Size::new(size, size),
+        icon.align,
+        icon.vertical_align,
+        icon.position.clone(),
+    );
+
+    let pixels = (0..raster.height)
+        .flat_map(|y| (0..raster.width).map(move |x| (x, y)))
+        .filter(|(x, y)| matches!(raster.get(*x, *y), [gray, alpha] if alpha >= 128 && gray < 128))
+        .map(|(x, y)| {
+            embedded_graphics::Pixel(
+                embedded_graphics::geometry::Point {
+                    x: origin.x + x as i32,
+                    y: origin.y + y as i32,
+                },
+                color,
+            )
+        });
+    display.draw_iter(pixels).map_err(|e| e.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::super::tests::*;
+    use super::*;
+    use yaml_merge_keys::serde_yaml;
+
+    #[test]
+    fn test_builtin() {
+        for (name, _) in ICONS {
+            let icon = Icon {
+                name: name.to_string(),
+                size: Some(12),
+                ..Default::default()
+            };
+            let raster = load_icon(&icon).unwrap();
+            assert!(
+                raster.pixels.iter().any(|[_, alpha]| *alpha >= 128),
+                "{} is empty",
+                name
+            );
+        }
+        for name in ["unknown", "../wifi-3", ""] {
+            let icon = Icon {
+                name: name.to_string(),
+                ..Default::default()
+            };
+            assert!(matches!(
+                icon_image(&icon),
+                Err(DrawingError::UnknownIcon(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_user_dir() {
+        let dir = std::env::temp_dir().join(format!("icons-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("check.pbm"), "P1 2 2 1 0 0 1").unwrap();
+        set_icon_dirs(vec![dir.clone()]);
+
+        let icon = |name: &str| Icon {
+            name: name.to_string(),
+            size: Some(2),
+            ..Default::default()
+        };
+        let image = icon_image(&icon("check")).unwrap();
+        assert!(image.path.ends_with("check.pbm"));
+        assert_eq!(
+            load_icon(&icon("check")).unwrap().pixels,
+            vec![[0, 255], [255, 255], [255, 255], [0, 255]]
+        );
+        // Other icons are still built in
+        assert!(icon_image(&icon("cross")).unwrap().svg.is_some());
+
+        set_icon_dirs(Vec::new());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_render() {
+        let template = parse(
+            serde_yaml::from_str(
+                r#"
+        - container:
+            position: {x: 0, y: 0}
+            size: {width: 48, height: 16}
+            background: "0"
+            style:
+              color: "1"
+            content:
+              - !icon
+                name: wifi-2
+                position: {x: 0, y: 0}
+                size: 16
+              - !icon
+                name: battery-3
+                position: {x: 16, y: 0}
+                size: 16
+              - !icon
+                name: warning
+                position: {x: 40, y: 8}
+                align: center
+                vertical_align: middle
+                size: 12
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let display = render(
+            embedded_graphics::prelude::Size::new(48, 16),
+            template,
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+                                                
+                                                
+                 ▄▄▄▄▄▄▄▄▄▄▄▄         ▄██       
+     ▄▄▄▄▄▄      ██▄▄▄▄▄▄   █▄▄      ▄████▄     
+   ▀█▀▀▀▀▀▀█▀    ██▀▀▀▀▀▀   █▀▀     ▄██████▄    
+       ▄▄        ▀▀▀▀▀▀▀▀▀▀▀▀      ▄████████▄   
+      ▀██▀                         ▀▀▀▀▀▀▀▀▀▀   
+                                                
+"#
+        );
+    }
+}
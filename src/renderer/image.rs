@@ -1,23 +1,249 @@
-use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::{error::DrawingError, renderer::positioning::place_rectangle};
 
+use super::grid::GridCell;
 use super::{
-    positioning::{HorizontalAlignment, VerticalAlignment},
-    ColorFromTemplate, Point,
+    decode::{decode, decode_bitmap, decode_data},
+    image_cache::cached,
+    positioning::{Direction, HorizontalAlignment, Length, OptionalLength, VerticalAlignment},
+    raster::{Dither, Raster, Resample},
+    resources::resolve,
+    svg, ColorFromTemplate, Point,
 };
-use embedded_graphics::prelude::{DrawTarget, PixelColor};
-use png::{BitDepth, Transformations};
+use embedded_graphics::prelude::{DrawTarget, PixelColor, Size};
+use embedded_graphics::primitives::Rectangle;
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Fit {
+    /// Whole image, centered in the box
+    Contain,
+    /// Fill the box, cropping the center of the image
+    Cover,
+    /// Fill the box, ignoring the aspect ratio
+    Stretch,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Size of a raw 1-bit bitmap
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+}
+
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Image {
+    /// PNG, GIF, JPEG, BMP, netpbm or SVG file
+    #[serde(default)]
     pub path: String,
+    /// SVG document, used instead of the file
+    pub svg: Option<String>,
+    /// Image in any supported format, as base64 or a data URI, used instead of the file
+    pub data: Option<String>,
+    /// Read `data` as packed 1-bit rows of this size, 1 for black, each row padded to a byte
+    pub bitmap: Option<Bitmap>,
     pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
     pub align: Option<HorizontalAlignment>,
     pub vertical_align: Option<VerticalAlignment>,
     pub invert: Option<bool>,
+
+    /// Box the image is scaled into. With only one of them, the aspect ratio is kept
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    /// How the image fills the box when both width and height are set. Default to contain
+    pub fit: Option<Fit>,
+    /// Part of the file to use, in its own pixels
+    pub crop: Option<Crop>,
+    /// Quarter turns clockwise, after the crop
+    pub rotate: Option<i32>,
+    /// Mirror the image, after the rotation
+    pub flip: Option<Direction>,
+    /// Default to area
+    pub resample: Option<Resample>,
+    /// Gray level from which pixels are white. Default to 128
+    pub threshold: Option<u8>,
+    /// Default to none
+    pub dither: Option<Dither>,
+}
+
+fn crop_rectangle(crop: &Crop) -> Rectangle {
+    Rectangle::new(
+        embedded_graphics::prelude::Point::new(crop.x as i32, crop.y as i32),
+        Size::new(crop.width, crop.height),
+    )
+}
+
+// (scaled image, box) sizes
+fn fit(image: &Image, oriented: Size) -> (Size, Size) {
+    let scaled = |scale: f64| {
+        Size::new(
+            (oriented.width as f64 * scale).round() as u32,
+            (oriented.height as f64 * scale).round() as u32,
+        )
+    };
+    match (image.width.pixels(), image.height.pixels()) {
+        (None, None) => (oriented, oriented),
+        _ if oriented.width == 0 || oriented.height == 0 => (oriented, oriented),
+        (Some(width), None) => {
+            let size = scaled(width as f64 / oriented.width as f64);
+            (size, size)
+        }
+        (None, Some(height)) => {
+            let size = scaled(height as f64 / oriented.height as f64);
+            (size, size)
+        }
+        (Some(width), Some(height)) => {
+            let frame = Size::new(width, height);
+            let scale_x = width as f64 / oriented.width as f64;
+            let scale_y = height as f64 / oriented.height as f64;
+            match image.fit.unwrap_or(Fit::Contain) {
+                Fit::Contain => (scaled(scale_x.min(scale_y)), frame),
+                Fit::Cover => (scaled(scale_x.max(scale_y)), frame),
+                Fit::Stretch => (frame, frame),
+            }
+        }
+    }
+}
+
+/// Area covered by the image
+pub fn image_bounds(image: &Image) -> Rectangle {
+    let size = match load(image) {
+        Ok(raster) => Size::new(raster.width, raster.height),
+        Err(e) => {
+            println!("Unable to read the size of {}: {:?}", source(image), e);
+            Size::zero()
+        }
+    };
+    let origin = place_rectangle(
+        size,
+        image.align,
+        image.vertical_align,
+        image.position.clone(),
+    );
+    Rectangle::new(origin.into(), size)
+}
+
+// Name of the image in errors
+fn source(image: &Image) -> String {
+    match (&image.svg, &image.data) {
+        (Some(_), _) => "<inline svg>".to_string(),
+        (None, Some(_)) => "<inline data>".to_string(),
+        (None, None) => image.path.clone(),
+    }
+}
+
+/// Decoded and transformed image, from the cache when possible
+pub fn load(image: &Image) -> Result<Arc<Raster>, DrawingError> {
+    match (&image.svg, &image.data) {
+        (None, None) => load_file(image, &resolve(&image.path)?),
+        _ => cached(image, None, || make(image, None)),
+    }
+}
+
+/// Same as load, reading the file at a path already checked
+pub fn load_file(image: &Image, file: &Path) -> Result<Arc<Raster>, DrawingError> {
+    cached(image, Some(file), || make(image, Some(file)))
+}
+
+fn make(image: &Image, file: Option<&Path>) -> Result<Raster, DrawingError> {
+    let invalid = |reason: String| DrawingError::InvalidImage(source(image), reason);
+    let bytes = match (&image.svg, &image.data) {
+        (Some(svg), _) => svg.as_bytes().to_vec(),
+        (None, Some(data)) => decode_data(data).map_err(invalid)?,
+        (None, None) => std::fs::read(file.unwrap_or(Path::new(&image.path)))
+            .map_err(|e| DrawingError::ResourceError(image.path.clone(), e))?,
+    };
+    if let Some(bitmap) = &image.bitmap {
+        let raster = decode_bitmap(bitmap.width, bitmap.height, &bytes).map_err(invalid)?;
+        return Ok(transform(image, raster));
+    }
+    if svg::is_svg(&bytes) {
+        let tree = svg::parse(&bytes).map_err(|e| invalid(e.to_string()))?;
+        return Ok(rasterize_svg(image, &tree));
+    }
+    Ok(transform(image, decode(&source(image), &bytes)?))
+}
+
+/// Render the vector image directly at its final size, then orient it
+fn rasterize_svg(image: &Image, tree: &resvg::usvg::Tree) -> Raster {
+    let (width, height) = svg::size(tree);
+    let area = match &image.crop {
+        Some(crop) => (
+            crop.x as f64,
+            crop.y as f64,
+            crop.width as f64,
+            crop.height as f64,
+        ),
+        None => (0.0, 0.0, width, height),
+    };
+    let quarter_turn = image.rotate.unwrap_or(0).rem_euclid(2) == 1;
+    let oriented = match quarter_turn {
+        true => Size::new(area.3.round() as u32, area.2.round() as u32),
+        false => Size::new(area.2.round() as u32, area.3.round() as u32),
+    };
+    let (scaled, _) = fit(image, oriented);
+    let scale = (
+        scaled.width as f64 / oriented.width.max(1) as f64,
+        scaled.height as f64 / oriented.height.max(1) as f64,
+    );
+    let scale = match quarter_turn {
+        true => (scale.1, scale.0),
+        false => scale,
+    };
+    orient(image, svg::rasterize(tree, area, scale))
+}
+
+/// Crop, orient and scale the decoded file into the box of the image
+fn transform(image: &Image, raster: Raster) -> Raster {
+    let raster = match &image.crop {
+        Some(crop) => raster.crop(&crop_rectangle(crop)),
+        None => raster,
+    };
+    orient(image, raster)
+}
+
+/// Rotate, flip and scale the cropped image into its box
+pub fn orient(image: &Image, raster: Raster) -> Raster {
+    let raster = raster.rotate(image.rotate.unwrap_or(0));
+    let raster = match image.flip {
+        Some(direction) => raster.flip(direction),
+        None => raster,
+    };
+
+    let (scaled, frame) = fit(image, Size::new(raster.width, raster.height));
+    let raster = raster.resize(
+        scaled.width,
+        scaled.height,
+        image.resample.unwrap_or(Resample::Area),
+    );
+    if scaled == frame {
+        return raster;
+    }
+    // Center the scaled image in the box, cropping or leaving transparent margins
+    let left = (scaled.width as i32 - frame.width as i32) / 2;
+    let top = (scaled.height as i32 - frame.height as i32) / 2;
+    Raster::from_fn(frame.width, frame.height, |x, y| {
+        let (x, y) = (x as i32 + left, y as i32 + top);
+        if x < 0 || y < 0 || x >= scaled.width as i32 || y >= scaled.height as i32 {
+            [0, 0]
+        } else {
+            raster.get(x as u32, y as u32)
+        }
+    })
 }
 
 pub fn draw_image<D, TargetColor>(display: &mut D, image: &Image) -> Result<(), DrawingError>
@@ -25,87 +251,63 @@ where
     D: DrawTarget<Color = TargetColor, Error: Into<DrawingError>>,
     TargetColor: PixelColor + ColorFromTemplate,
 {
-    let mut decoder = png::Decoder::new(
-        File::open(&image.path).map_err(|e| DrawingError::ResourceError(image.path.clone(), e))?,
+    let raster = load(image)?;
+    let origin = place_rectangle(
+        Size::new(raster.width, raster.height),
+        image.align,
+        image.vertical_align,
+        image.position.clone(),
     );
-    decoder.set_transformations(Transformations::normalize_to_color8());
-    let mut reader = decoder
-        .read_info()
-        .map_err(|e| DrawingError::ImageError(image.path.clone(), e))?;
-    // Allocate the output buffer.
-    let mut buf = vec![0; reader.output_buffer_size()];
-    // Read the next frame. An APNG might contain multiple frames.
-    let info = reader
-        .next_frame(&mut buf)
-        .map_err(|e| DrawingError::ImageError(image.path.clone(), e))?;
-    // Grab the bytes of the image.
-    let bytes = &buf[..info.buffer_size()];
-
-    if info.bit_depth != BitDepth::Eight {
-        println!("Unsupported bit depth: {:?}", info.bit_depth);
-        return Ok(());
-    }
+    draw_raster(
+        display,
+        &raster,
+        origin.into(),
+        raster.binarize(
+            image.threshold.unwrap_or(128),
+            image.dither.unwrap_or(Dither::None),
+        ),
+        image.invert.unwrap_or(false),
+    )
+}
 
+/// Draw the black and white pixels of a binarized raster from its top left corner
+pub fn draw_raster<D, TargetColor>(
+    display: &mut D,
+    raster: &Raster,
+    origin: embedded_graphics::prelude::Point,
+    binary: Vec<Option<bool>>,
+    invert: bool,
+) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: Into<DrawingError>>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
     let (mut back, mut front) = (
         TargetColor::resolve(&Some("0".to_string())),
         TargetColor::resolve(&Some("1".to_string())),
     );
-    if image.invert.unwrap_or(false) {
+    if invert {
         std::mem::swap(&mut back, &mut front);
     }
 
-    let origin = place_rectangle(
-        embedded_graphics::geometry::Size {
-            width: info.width,
-            height: info.height,
-        },
-        image.align,
-        image.vertical_align,
-        image.position.clone(),
-    );
-
     let mut pixels = Vec::with_capacity(256);
 
-    for y in 0..info.height {
-        let mut pos = y as usize * info.line_size;
-        for x in 0..info.width {
-            let color = match info.color_type {
-                png::ColorType::Grayscale => Some(bytes[pos] >= 128),
-                png::ColorType::GrayscaleAlpha => {
-                    if bytes[pos + 1] < 128 {
-                        None
-                    } else {
-                        Some(bytes[pos] >= 128)
-                    }
-                }
-                png::ColorType::Indexed => Some(bytes[pos] != 0),
-                png::ColorType::Rgb => {
-                    Some(bytes[pos] >= 128 || bytes[pos + 1] >= 128 || bytes[pos + 2] >= 128)
-                }
-                png::ColorType::Rgba => {
-                    if bytes[pos + 3] < 128 {
-                        None
-                    } else {
-                        Some(bytes[pos] >= 128 || bytes[pos + 1] >= 128 || bytes[pos + 2] >= 128)
-                    }
-                }
+    for y in 0..raster.height {
+        for x in 0..raster.width {
+            let Some(white) = binary[(y * raster.width + x) as usize] else {
+                continue;
             };
-
-            if color.is_some() {
-                pixels.push(embedded_graphics::Pixel(
-                    embedded_graphics::geometry::Point {
-                        x: origin.x + x as i32,
-                        y: origin.y + y as i32,
-                    },
-                    if color.unwrap() { front } else { back },
-                ));
-                if pixels.len() >= 256 {
-                    display.draw_iter(pixels).map_err(|e| e.into())?;
-                    pixels = Vec::with_capacity(256);
-                }
+            pixels.push(embedded_graphics::Pixel(
+                embedded_graphics::geometry::Point {
+                    x: origin.x + x as i32,
+                    y: origin.y + y as i32,
+                },
+                if white { front } else { back },
+            ));
+            if pixels.len() >= 256 {
+                display.draw_iter(pixels).map_err(|e| e.into())?;
+                pixels = Vec::with_capacity(256);
             }
-
-            pos += info.color_type.samples();
         }
     }
 
@@ -117,10 +319,12 @@ where
 #[cfg(test)]
 mod tests {
 
+    use super::super::raster::Resample;
     use super::super::tests::*;
     use super::super::Primitive;
     use super::super::*;
     use super::*;
+    use yaml_merge_keys::serde_yaml;
 
     #[test]
     fn test_png() {
@@ -131,10 +335,15 @@ mod tests {
             },
             vec![Primitive::Image(Image {
                 path: "resources/wifi-small.png".to_string(),
-                position: Point { x: 16, y: 16 },
+                position: Point {
+                    x: 16,
+                    y: 16,
+                    ..Default::default()
+                },
                 align: Some(HorizontalAlignment::Center),
                 vertical_align: Some(VerticalAlignment::Middle),
                 invert: None,
+                ..Default::default()
             })],
             None,
         );
@@ -158,6 +367,172 @@ mod tests {
 ██████████████    ██████████████
 ████████████████████████████████
 ████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_scaled() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 56,
+                height: 16,
+            },
+            vec![
+                Primitive::Image(Image {
+                    path: "resources/wifi-small.png".to_string(),
+                    width: Some(16.into()),
+                    height: Some(16.into()),
+                    ..Default::default()
+                }),
+                Primitive::Image(Image {
+                    path: "resources/wifi-small.png".to_string(),
+                    position: Point {
+                        x: 16,
+                        ..Default::default()
+                    },
+                    height: Some(16.into()),
+                    crop: Some(Crop {
+                        x: 0,
+                        y: 0,
+                        width: 13,
+                        height: 23,
+                    }),
+                    rotate: Some(1),
+                    resample: Some(Resample::Nearest),
+                    ..Default::default()
+                }),
+                Primitive::Image(Image {
+                    path: "resources/wifi-small.png".to_string(),
+                    position: Point {
+                        x: 46,
+                        ..Default::default()
+                    },
+                    width: Some(8.into()),
+                    height: Some(16.into()),
+                    fit: Some(Fit::Cover),
+                    flip: Some(Direction::Vertical),
+                    ..Default::default()
+                }),
+            ],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+█████▀▀▀▀▀▀███████████████████████   ▀██████████▀  ▀████
+█▀▀  ▄▄▄▄▄▄  ▀▀███████████████████     █████████▄  ▄████
+  ▄█▀▀▀▀▀▀▀▀█▄  █████████████▀▀▀███▄▄   ▀▀████▀▀████▀▀██
+███  ▄████▄  ████████████████    ▀████▄   ▀████▄    ▄███
+█████▀    ▀██████████████████▄▄   ▀████    ███▀▀████▀▀██
+█████▄████▄██████████████   ▀███▄  ▀▀██▄   ▀██▄      ▄██
+██████    ████████   █████   ████    ███    ██▀██████▀██
+███████▄▄███████      ▀███▄  ████    ███    ██▄      ▄██
+"#
+        );
+    }
+
+    #[test]
+    fn test_svg() {
+        let gradient = r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="1">
+          <defs><linearGradient id="g"><stop offset="0" stop-color="black"/><stop offset="1" stop-color="white"/></linearGradient></defs>
+          <rect width="4" height="1" fill="url(#g)"/>
+        </svg>"#;
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 48,
+                height: 16,
+            },
+            vec![
+                Primitive::Image(Image {
+                    path: "resources/mdi--ethernet.svg".to_string(),
+                    height: Some(16.into()),
+                    ..Default::default()
+                }),
+                Primitive::Image(Image {
+                    svg: Some(gradient.to_string()),
+                    position: Point {
+                        x: 16,
+                        ..Default::default()
+                    },
+                    width: Some(32.into()),
+                    height: Some(8.into()),
+                    fit: Some(Fit::Stretch),
+                    dither: Some(Dither::Ordered),
+                    ..Default::default()
+                }),
+                Primitive::Image(Image {
+                    svg: Some(gradient.to_string()),
+                    position: Point {
+                        x: 16,
+                        y: 8,
+                        ..Default::default()
+                    },
+                    width: Some(32.into()),
+                    height: Some(8.into()),
+                    fit: Some(Fit::Stretch),
+                    threshold: Some(64),
+                    ..Default::default()
+                }),
+            ],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████      ▄ ▄ ▄▀▄ ▄▀▄▀▄█▄▀▄█▄█▄█▄███
+█▀            ▀█    ▄ ▄ ▄▀▄ ▄▀▄▀▄█▄▀▄█▄█▄█▄█████
+█     ████     █      ▄ ▄ ▄▀▄ ▄▀▄▀▄█▄▀▄█▄█▄█▄███
+█  ██████████  █    ▄ ▄ ▄▀▄ ▄▀▄▀▄█▄▀▄█▄█▄█▄█████
+█  ██████████  █        ████████████████████████
+█  ██ █  █ ██  █        ████████████████████████
+█              █        ████████████████████████
+██▄▄▄▄▄▄▄▄▄▄▄▄██        ████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_data() {
+        let template = parse(
+            serde_yaml::from_str(
+                r#"
+        - image:
+            data: "PEKlgaWZQjw="
+            bitmap: {width: 8, height: 8}
+            position: {x: 0, y: 0}
+            width: 16
+            resample: nearest
+        - image:
+            data: "data:image/x-portable-bitmap;base64,UDEgNCAyCjEgMCAxIDAKMCAxIDAgMQ=="
+            position: {x: 20, y: 4}
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 24,
+                height: 16,
+            },
+            template,
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████        ████████████
+██  ████████  ██████████
+  ██  ████  ██  ████▄▀▄▀
+  ████████████  ████████
+  ██  ████  ██  ████████
+  ████    ████  ████████
+██  ████████  ██████████
+████        ████████████
 "#
         );
     }
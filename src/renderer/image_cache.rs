@@ -0,0 +1,305 @@
+use axum::{http::StatusCode, routing::post, Router};
+use once_cell::sync::Lazy;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{device_driver::RefreshSignal, error::DrawingError, trigger_draw};
+
+use super::fits::{Fits, Stretch};
+use super::image::{Bitmap, Crop, Fit, Image};
+use super::positioning::{Direction, OptionalLength};
+use super::raster::{Raster, Resample};
+
+/// Most images kept, and most pixels over all of them
+const MAX_ENTRIES: usize = 64;
+const MAX_PIXELS: usize = 4 * 1024 * 1024;
+
+// Settings of the image that change the cached raster
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ImageOptions {
+    bitmap: Option<Bitmap>,
+    width: Option<u32>,
+    height: Option<u32>,
+    fit: Option<Fit>,
+    crop: Option<Crop>,
+    rotate: Option<i32>,
+    flip: Option<Direction>,
+    resample: Option<Resample>,
+}
+
+// Levels are kept as their bits, to be hashed
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct FitsOptions {
+    hdu: Option<usize>,
+    region: Option<Crop>,
+    width: Option<u32>,
+    height: Option<u32>,
+    stretch: Option<Stretch>,
+    black: Option<u64>,
+    white: Option<u64>,
+    target: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Options {
+    Image(ImageOptions),
+    Fits(FitsOptions),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Key {
+    /// Path of the file, or hash of an inline image
+    path: String,
+    modified: Option<SystemTime>,
+    options: Options,
+}
+
+struct Entry {
+    raster: Arc<Raster>,
+    last_use: u64,
+}
+
+#[derive(Default)]
+struct ImageCache {
+    entries: HashMap<Key, Entry>,
+    uses: u64,
+}
+
+impl ImageCache {
+    fn get(&mut self, key: &Key) -> Option<Arc<Raster>> {
+        self.uses += 1;
+        let uses = self.uses;
+        self.entries.get_mut(key).map(|entry| {
+            entry.last_use = uses;
+            entry.raster.clone()
+        })
+    }
+
+    fn insert(&mut self, key: Key, raster: Arc<Raster>) {
+        // Older versions of the file are useless
+        self.entries
+            .retain(|k, _| k.path != key.path || k.modified == key.modified);
+        self.uses += 1;
+        self.entries.insert(
+            key,
+            Entry {
+                raster,
+                last_use: self.uses,
+            },
+        );
+
+        // Drop the least recently used images, keeping at least the new one
+        while self.entries.len() > 1
+            && (self.entries.len() > MAX_ENTRIES || self.pixels() > MAX_PIXELS)
+        {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_use)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn pixels(&self) -> usize {
+        self.entries
+            .values()
+            .map(|entry| entry.raster.pixels.len())
+            .sum()
+    }
+
+    /// Forget the images of a path, or all of them. Inline images have no path, they are only
+    /// forgotten with all the others
+    fn invalidate(&mut self, path: Option<&str>) {
+        match path {
+            Some(path) => self.entries.retain(|key, _| key.path != path),
+            None => self.entries.clear(),
+        }
+    }
+}
+
+static CACHE: Lazy<Mutex<ImageCache>> = Lazy::new(|| Mutex::new(ImageCache::default()));
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|m| m.modified()).ok()
+}
+
+// Inline images are keyed by a hash of their content, rather than by the whole of it
+fn source(image: &Image) -> String {
+    let hash = |content: &str| {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    };
+    match (&image.svg, &image.data) {
+        (Some(svg), _) => format!("svg:{:016x}", hash(svg)),
+        (None, Some(data)) => format!("data:{:016x}", hash(data)),
+        (None, None) => image.path.clone(),
+    }
+}
+
+fn image_key(image: &Image, file: Option<&Path>) -> Key {
+    Key {
+        path: source(image),
+        modified: file.and_then(modified),
+        options: Options::Image(ImageOptions {
+            bitmap: image.bitmap.clone(),
+            width: image.width.pixels(),
+            height: image.height.pixels(),
+            fit: image.fit,
+            crop: image.crop.clone(),
+            rotate: image.rotate,
+            flip: image.flip,
+            resample: image.resample,
+        }),
+    }
+}
+
+/// Raster of the image read from the file, if any, from the cache or made by the given function
+pub fn cached(
+    image: &Image,
+    file: Option<&Path>,
+    make: impl FnOnce() -> Result<Raster, DrawingError>,
+) -> Result<Arc<Raster>, DrawingError> {
+    cached_key(image_key(image, file), make)
+}
+
+/// Stretched FITS frame, from the cache or made by the given function
+pub fn cached_fits(
+    fits: &Fits,
+    file: &Path,
+    make: impl FnOnce() -> Result<Raster, DrawingError>,
+) -> Result<Arc<Raster>, DrawingError> {
+    let key = Key {
+        path: fits.path.clone(),
+        modified: modified(file),
+        options: Options::Fits(FitsOptions {
+            hdu: fits.hdu,
+            region: fits.region.clone(),
+            width: fits.width.pixels(),
+            height: fits.height.pixels(),
+            stretch: fits.stretch,
+            black: fits.black.map(f64::to_bits),
+            white: fits.white.map(f64::to_bits),
+            target: fits.target.map(f64::to_bits),
+        }),
+    };
+    cached_key(key, make)
+}
+
+fn cached_key(
+    key: Key,
+    make: impl FnOnce() -> Result<Raster, DrawingError>,
+) -> Result<Arc<Raster>, DrawingError> {
+    if let Some(raster) = CACHE.lock().unwrap().get(&key) {
+        return Ok(raster);
+    }
+    // Decode without holding the lock
+    let raster = Arc::new(make()?);
+    CACHE.lock().unwrap().insert(key, raster.clone());
+    Ok(raster)
+}
+
+pub fn route(router: Router) -> Router {
+    router.route("/images/invalidate", post(post_invalidate))
+}
+
+/// Forget the cached images of the file path in the body, or all of them, inline images included,
+/// when the body is empty
+pub async fn post_invalidate(payload: String) -> Result<(), (StatusCode, String)> {
+    let path = payload.trim();
+    CACHE
+        .lock()
+        .unwrap()
+        .invalidate((!path.is_empty()).then_some(path));
+
+    trigger_draw(RefreshSignal::Normal);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str, modified: u64) -> Key {
+        Key {
+            path: path.to_string(),
+            modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(modified)),
+            options: Options::Image(ImageOptions {
+                bitmap: None,
+                width: None,
+                height: None,
+                fit: None,
+                crop: None,
+                rotate: None,
+                flip: None,
+                resample: None,
+            }),
+        }
+    }
+
+    fn raster(size: u32) -> Arc<Raster> {
+        Arc::new(Raster::from_fn(size, size, |_, _| [0, 255]))
+    }
+
+    #[test]
+    fn test_cache() {
+        let mut cache = ImageCache::default();
+        cache.insert(key("a.png", 1), raster(2));
+        cache.insert(key("b.png", 1), raster(2));
+        assert!(cache.get(&key("a.png", 1)).is_some());
+
+        // A newer file replaces the older version
+        cache.insert(key("a.png", 2), raster(2));
+        assert!(cache.get(&key("a.png", 1)).is_none());
+        assert_eq!(cache.entries.len(), 2);
+
+        cache.invalidate(Some("b.png"));
+        assert!(cache.get(&key("b.png", 1)).is_none());
+        cache.invalidate(None);
+        assert!(cache.entries.is_empty());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut cache = ImageCache::default();
+        for i in 0..MAX_ENTRIES as u64 {
+            cache.insert(key(&format!("{}.png", i), 1), raster(1));
+        }
+        // The first image is used, so the second one is the oldest
+        cache.get(&key("0.png", 1));
+        cache.insert(key("new.png", 1), raster(1));
+        assert_eq!(cache.entries.len(), MAX_ENTRIES);
+        assert!(cache.get(&key("0.png", 1)).is_some());
+        assert!(cache.get(&key("1.png", 1)).is_none());
+
+        // A huge image pushes out everything else
+        cache.insert(key("huge.png", 1), raster(2048));
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_inline_key() {
+        let image = |data: &str| Image {
+            data: Some(data.to_string()),
+            ..Default::default()
+        };
+        let key = image_key(&image("data:image/png;base64,AAAA"), None);
+        assert!(key.path.starts_with("data:") && key.path.len() == 21);
+        assert_eq!(key, image_key(&image("data:image/png;base64,AAAA"), None));
+        assert_ne!(key, image_key(&image("data:image/png;base64,AAAB"), None));
+
+        let svg = Image {
+            svg: Some("<svg/>".to_string()),
+            ..Default::default()
+        };
+        assert!(image_key(&svg, None).path.starts_with("svg:"));
+    }
+}
@@ -0,0 +1,38 @@
+use embedded_graphics::prelude::{Dimensions, DrawTarget, PixelColor};
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::Pixel;
+
+use super::ColorFromTemplate;
+
+/// Draw target that inverts the colors drawn on the wrapped display
+pub struct Inverted<'a, D>(pub &'a mut D);
+
+impl<D: Dimensions> Dimensions for Inverted<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.0.bounding_box()
+    }
+}
+
+impl<D, TargetColor> DrawTarget for Inverted<'_, D>
+where
+    D: DrawTarget<Color = TargetColor>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    type Color = TargetColor;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        self.0.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, color.invert())),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        self.0.fill_solid(area, color.invert())
+    }
+}
@@ -0,0 +1,295 @@
+use embedded_graphics::prelude::Size;
+use embedded_graphics::primitives::Rectangle;
+use serde::{Deserialize, Serialize};
+
+use super::container::Container;
+use super::fits::fits_bounds;
+use super::grid::{grid_size, grid_slots};
+use super::icon::icon_size;
+use super::image::image_bounds;
+use super::positioning::{place_rectangle, HorizontalAlignment, VerticalAlignment};
+use super::richtext::rich_text_bounds;
+use super::table::table_bounds;
+use super::text::text_bounds;
+use super::{Point, Primitive};
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Layout {
+    Absolute,
+    /// Children from left to right
+    Row,
+    /// Children from top to bottom
+    Column,
+    /// Children on top of each other
+    Stack,
+    /// Children in the cells of rows and columns
+    Grid,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemAlignment {
+    Start,
+    Center,
+    End,
+    /// Give children the whole space (only containers without size use it)
+    Stretch,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+}
+
+fn sized(
+    position: &Point,
+    width: u32,
+    height: u32,
+    align: Option<HorizontalAlignment>,
+    vertical_align: Option<VerticalAlignment>,
+) -> Rectangle {
+    let size = Size::new(width, height);
+    Rectangle::new(
+        place_rectangle(size, align, vertical_align, position.clone()).into(),
+        size,
+    )
+}
+
+/// Area covered by a primitive, in the coordinates of its parent
+pub fn measure(primitive: &Primitive) -> Rectangle {
+    match primitive {
+        Primitive::Dummy(_) => Rectangle::zero(),
+        Primitive::Text(text) => text_bounds(text),
+        Primitive::RichText(rich) => rich_text_bounds(rich),
+        Primitive::QRCode(qr) => sized(
+            &qr.position,
+            qr.width.pixels(),
+            qr.height.pixels(),
+            None,
+            None,
+        ),
+        Primitive::Barcode(barcode) => sized(
+            &barcode.position,
+            barcode.width.pixels(),
+            barcode.height.pixels(),
+            None,
+            None,
+        ),
+        Primitive::Image(image) => image_bounds(image),
+        Primitive::Fits(fits) => fits_bounds(fits),
+        Primitive::Icon(icon) => sized(
+            &icon.position,
+            icon_size(icon),
+            icon_size(icon),
+            icon.align,
+            icon.vertical_align,
+        ),
+        Primitive::Progress(progress) => sized(
+            &progress.position,
+            progress.width.pixels(),
+            progress.height.pixels(),
+            progress.align,
+            progress.vertical_align,
+        ),
+        Primitive::Chart(chart) => sized(
+            &chart.position,
+            chart.width.pixels(),
+            chart.height.pixels(),
+            None,
+            None,
+        ),
+        Primitive::Bars(bars) => sized(
+            &bars.position,
+            bars.width.pixels(),
+            bars.height.pixels(),
+            bars.align,
+            bars.vertical_align,
+        ),
+        Primitive::Gauge(gauge) => sized(
+            &gauge.position,
+            gauge.diameter.pixels(),
+            gauge.diameter.pixels(),
+            gauge.align,
+            gauge.vertical_align,
+        ),
+        Primitive::Clock(clock) => sized(
+            &clock.position,
+            clock.diameter.pixels(),
+            clock.diameter.pixels(),
+            clock.align,
+            clock.vertical_align,
+        ),
+        Primitive::Table(table) => table_bounds(table),
+        Primitive::Container(container) => container_bounds(container),
+    }
+}
+
+fn container_bounds(container: &Container) -> Rectangle {
+    let size = match &container.size {
+        Some(size) => size.clone().into(),
+        None => {
+            let style = container.style.clone().unwrap_or_default();
+            let content: Vec<Primitive> = container
+                .content
+                .iter()
+                .flatten()
+                .filter(|c| !matches!(c, Primitive::Dummy(_)))
+                .map(|c| {
+                    let mut child = c.clone();
+                    style.apply(&mut child);
+                    child
+                })
+                .collect();
+            let primitives: Vec<&Primitive> = content.iter().collect();
+            let children: Vec<Rectangle> = primitives.iter().map(|c| measure(c)).collect();
+            let spacing = container.spacing.unwrap_or(0);
+            let gaps = spacing * (children.len() as u32).saturating_sub(1);
+            let widths = children.iter().map(|c| c.size.width);
+            let heights = children.iter().map(|c| c.size.height);
+            let content = match container.layout.unwrap_or(Layout::Absolute) {
+                Layout::Absolute => Size::new(
+                    children
+                        .iter()
+                        .map(|c| (c.top_left.x + c.size.width as i32).max(0) as u32)
+                        .max()
+                        .unwrap_or(0),
+                    children
+                        .iter()
+                        .map(|c| (c.top_left.y + c.size.height as i32).max(0) as u32)
+                        .max()
+                        .unwrap_or(0),
+                ),
+                Layout::Row => Size::new(widths.sum::<u32>() + gaps, heights.max().unwrap_or(0)),
+                Layout::Column => Size::new(widths.max().unwrap_or(0), heights.sum::<u32>() + gaps),
+                Layout::Grid => grid_size(container, &primitives, &children),
+                Layout::Stack => Size::new(widths.max().unwrap_or(0), heights.max().unwrap_or(0)),
+            };
+            let inset = container.padding.unwrap_or(0) + container.border.unwrap_or(0);
+            let size = content + Size::new_equal(2 * inset);
+            match container.rotate.unwrap_or(0) % 2 {
+                0 => size,
+                _ => Size::new(size.height, size.width),
+            }
+        }
+    };
+    Rectangle::new(container.position.clone().into(), size)
+}
+
+fn grow(primitive: &Primitive) -> u32 {
+    match primitive {
+        Primitive::Container(container) => container.grow.unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// (offset, size) of an item inside a space, along one axis
+pub fn place(alignment: ItemAlignment, space: u32, size: u32) -> (i32, u32) {
+    match alignment {
+        ItemAlignment::Start => (0, size),
+        ItemAlignment::Center => ((space as i32 - size as i32) / 2, size),
+        ItemAlignment::End => (space as i32 - size as i32, size),
+        ItemAlignment::Stretch => (0, space),
+    }
+}
+
+/// Slots of the children of a row, column or stack container, in an area of the given size
+pub fn flow(
+    container: &Container,
+    layout: Layout,
+    area: Size,
+    children: &[&Primitive],
+    bounds: &[Rectangle],
+) -> Vec<Rectangle> {
+    let align = container.align_items.unwrap_or(ItemAlignment::Start);
+
+    let row = match layout {
+        Layout::Row => true,
+        Layout::Column => false,
+        Layout::Grid => return grid_slots(container, area, children, bounds),
+        Layout::Absolute | Layout::Stack => {
+            return bounds
+                .iter()
+                .map(|b| {
+                    let (x, width) = place(align, area.width, b.size.width);
+                    let (y, height) = place(align, area.height, b.size.height);
+                    Rectangle::new(
+                        embedded_graphics::prelude::Point::new(x, y),
+                        Size::new(width, height),
+                    )
+                })
+                .collect();
+        }
+    };
+
+    // Work with (main, cross) axes
+    let split = |s: Size| {
+        if row {
+            (s.width, s.height)
+        } else {
+            (s.height, s.width)
+        }
+    };
+    let (area_main, area_cross) = split(area);
+    let count = bounds.len() as u32;
+    let spacing = container.spacing.unwrap_or(0);
+    let used =
+        bounds.iter().map(|b| split(b.size).0).sum::<u32>() + spacing * count.saturating_sub(1);
+    let free = area_main.saturating_sub(used);
+
+    let grows: Vec<u32> = children.iter().map(|c| grow(c)).collect();
+    let total_grow: u32 = grows.iter().sum();
+    let (mut main, gap) = if total_grow > 0 {
+        (0, spacing)
+    } else {
+        match container.justify.unwrap_or(Justify::Start) {
+            Justify::Start => (0, spacing),
+            Justify::Center => (free / 2, spacing),
+            Justify::End => (free, spacing),
+            Justify::SpaceBetween if count > 1 => (0, spacing + free / (count - 1)),
+            Justify::SpaceBetween => (0, spacing),
+        }
+    };
+
+    let mut given = 0;
+    let mut grown = 0;
+    bounds
+        .iter()
+        .zip(grows.iter())
+        .map(|(b, grow)| {
+            let (size_main, size_cross) = split(b.size);
+            // The last growing child takes what rounding left
+            let extra = if *grow == 0 {
+                0
+            } else {
+                grown += grow;
+                let extra = if grown == total_grow {
+                    free - given
+                } else {
+                    free * grow / total_grow
+                };
+                given += extra;
+                extra
+            };
+            let length = size_main + extra;
+            let (cross, cross_length) = place(align, area_cross, size_cross);
+            let slot = if row {
+                Rectangle::new(
+                    embedded_graphics::prelude::Point::new(main as i32, cross),
+                    Size::new(length, cross_length),
+                )
+            } else {
+                Rectangle::new(
+                    embedded_graphics::prelude::Point::new(cross, main as i32),
+                    Size::new(cross_length, length),
+                )
+            };
+            main += length + gap;
+            slot
+        })
+        .collect()
+}
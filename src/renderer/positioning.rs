@@ -1,7 +1,14 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::str::FromStr;
+
 use embedded_graphics::prelude::Size;
-use serde::{Deserialize, Serialize};
+use embedded_graphics::primitives::Rectangle;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::Point;
+use super::container::Container;
+use super::{Point, Primitive};
 
 #[derive(Debug, Copy, Clone, Ord, Serialize, Deserialize, PartialOrd, Eq, PartialEq, Hash)]
 #[serde(rename_all = "camelCase")]
@@ -43,5 +50,619 @@ pub fn place_rectangle(
             Some(VerticalAlignment::Middle) => pos.y - (s.height / 2) as i32,
             Some(VerticalAlignment::Bottom) => pos.y - s.height as i32,
         },
+        relative: None,
+    }
+}
+
+/// Pixels, or a percentage of the parent extent plus an offset in pixels.
+/// Written 12, "50%", "50%-4", or with an anchor: "left", "center", "right-8", "top",
+/// "middle", "bottom+2" (anchors stand for 0%, 50% and 100% on either axis)
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Coordinate {
+    /// Set when written as a percentage or an anchor, even 0%: the coordinate then starts at
+    /// the parent origin
+    pub percent: Option<f64>,
+    pub offset: i32,
+}
+
+impl Coordinate {
+    pub fn pixels(offset: i32) -> Coordinate {
+        Coordinate {
+            percent: None,
+            offset,
+        }
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.percent.is_some()
+    }
+
+    /// Pixels from the start of an extent
+    pub fn resolve(&self, extent: u32) -> i32 {
+        (self.percent.unwrap_or(0.0) * extent as f64 / 100.0).round() as i32 + self.offset
+    }
+}
+
+impl FromStr for Coordinate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid coordinate: {:?}", s);
+        let compact: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        // The offset starts at the first sign after the base
+        let split = compact
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '+' || *c == '-')
+            .map(|(i, _)| i)
+            .unwrap_or(compact.len());
+        let (base, offset) = compact.split_at(split);
+        let offset = match offset {
+            "" => 0,
+            offset => offset
+                .trim_start_matches('+')
+                .parse()
+                .map_err(|_| invalid())?,
+        };
+        let percent = match base {
+            "left" | "top" => 0.0,
+            "center" | "middle" => 50.0,
+            "right" | "bottom" => 100.0,
+            base => match base.strip_suffix('%') {
+                Some(percent) => percent.parse().map_err(|_| invalid())?,
+                None => {
+                    return Ok(Coordinate::pixels(
+                        base.parse::<i32>().map_err(|_| invalid())? + offset,
+                    ))
+                }
+            },
+        };
+        Ok(Coordinate {
+            percent: Some(percent),
+            offset,
+        })
+    }
+}
+
+impl Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.percent, self.offset) {
+            (None, offset) => write!(f, "{}", offset),
+            (Some(percent), 0) => write!(f, "{}%", percent),
+            (Some(percent), offset) => write!(f, "{}%{:+}", percent, offset),
+        }
+    }
+}
+
+impl Serialize for Coordinate {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self.is_relative() {
+            self.to_string().serialize(s)
+        } else {
+            self.offset.serialize(s)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Coordinate {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum CoordinateValue {
+            Pixels(i32),
+            Text(String),
+        }
+
+        match CoordinateValue::deserialize(d)? {
+            CoordinateValue::Pixels(pixels) => Ok(Coordinate::pixels(pixels)),
+            CoordinateValue::Text(text) => text.parse().map_err(D::Error::custom),
+        }
+    }
+}
+
+// Point and Size as written in templates
+#[derive(Serialize, Deserialize)]
+pub struct PointSpec {
+    x: Coordinate,
+    y: Coordinate,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SizeSpec {
+    width: Coordinate,
+    height: Coordinate,
+}
+
+// Pixels are kept in the fields, relative coordinates on the side
+fn split(a: Coordinate, b: Coordinate) -> (i32, i32, Option<[Coordinate; 2]>) {
+    let relative = (a.is_relative() || b.is_relative()).then_some([a, b]);
+    (a.offset, b.offset, relative)
+}
+
+impl From<PointSpec> for Point {
+    fn from(spec: PointSpec) -> Point {
+        let (x, y, relative) = split(spec.x, spec.y);
+        Point { x, y, relative }
+    }
+}
+
+impl From<Point> for PointSpec {
+    fn from(point: Point) -> PointSpec {
+        let [x, y] = point
+            .relative
+            .unwrap_or([Coordinate::pixels(point.x), Coordinate::pixels(point.y)]);
+        PointSpec { x, y }
+    }
+}
+
+impl From<SizeSpec> for super::Size {
+    fn from(spec: SizeSpec) -> super::Size {
+        let (width, height, relative) = split(spec.width, spec.height);
+        super::Size {
+            width,
+            height,
+            relative,
+        }
+    }
+}
+
+impl From<super::Size> for SizeSpec {
+    fn from(size: super::Size) -> SizeSpec {
+        let [width, height] = size.relative.unwrap_or([
+            Coordinate::pixels(size.width),
+            Coordinate::pixels(size.height),
+        ]);
+        SizeSpec { width, height }
+    }
+}
+
+impl Point {
+    /// Pixels in the given bounds of the parent
+    pub fn resolve(&self, bounds: &Rectangle) -> Point {
+        match &self.relative {
+            None => self.clone(),
+            Some([x, y]) => Point {
+                x: match x.is_relative() {
+                    true => bounds.top_left.x + x.resolve(bounds.size.width),
+                    false => x.offset,
+                },
+                y: match y.is_relative() {
+                    true => bounds.top_left.y + y.resolve(bounds.size.height),
+                    false => y.offset,
+                },
+                relative: None,
+            },
+        }
+    }
+}
+
+impl super::Size {
+    /// Pixels in the given bounds of the parent
+    pub fn resolve(&self, bounds: &Rectangle) -> super::Size {
+        match &self.relative {
+            None => self.clone(),
+            Some([width, height]) => super::Size {
+                width: width.resolve(bounds.size.width),
+                height: height.resolve(bounds.size.height),
+                relative: None,
+            },
+        }
+    }
+}
+
+/// Width, height or diameter: pixels, or a percentage of the parent extent plus an offset
+/// (see Coordinate). Replaced by pixels before drawing
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Length(Coordinate);
+
+impl Length {
+    /// Pixels of a resolved length
+    pub fn pixels(&self) -> u32 {
+        self.0.offset.max(0) as u32
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.0.is_relative()
+    }
+
+    pub fn resolve(&self, extent: u32) -> Length {
+        Length(Coordinate::pixels(self.0.resolve(extent).max(0)))
+    }
+}
+
+/// Pixels of an optional resolved length
+pub trait OptionalLength {
+    fn pixels(&self) -> Option<u32>;
+}
+
+impl OptionalLength for Option<Length> {
+    fn pixels(&self) -> Option<u32> {
+        self.map(|length| length.pixels())
+    }
+}
+
+impl From<u32> for Length {
+    fn from(pixels: u32) -> Length {
+        Length(Coordinate::pixels(pixels as i32))
+    }
+}
+
+impl From<Coordinate> for Length {
+    fn from(coordinate: Coordinate) -> Length {
+        Length(coordinate)
+    }
+}
+
+// Extent of the parent a length is relative to
+#[derive(Debug, Copy, Clone)]
+enum Extent {
+    Width,
+    Height,
+    /// For diameters
+    Smallest,
+}
+
+impl Extent {
+    fn of(&self, bounds: &Rectangle) -> u32 {
+        match self {
+            Extent::Width => bounds.size.width,
+            Extent::Height => bounds.size.height,
+            Extent::Smallest => bounds.size.width.min(bounds.size.height),
+        }
+    }
+}
+
+use Extent::{Height, Smallest, Width};
+
+// Position and lengths of a primitive
+fn parts(primitive: &Primitive) -> (Option<&Point>, Vec<(Option<&Length>, Extent)>) {
+    match primitive {
+        Primitive::Dummy(_) => (None, vec![]),
+        Primitive::Text(text) => (
+            Some(&text.position),
+            vec![(text.width.as_ref(), Width), (text.height.as_ref(), Height)],
+        ),
+        Primitive::RichText(rich) => (Some(&rich.position), vec![(rich.width.as_ref(), Width)]),
+        Primitive::QRCode(qr) => (
+            Some(&qr.position),
+            vec![(Some(&qr.width), Width), (Some(&qr.height), Height)],
+        ),
+        Primitive::Barcode(barcode) => (
+            Some(&barcode.position),
+            vec![
+                (Some(&barcode.width), Width),
+                (Some(&barcode.height), Height),
+            ],
+        ),
+        Primitive::Image(image) => (
+            Some(&image.position),
+            vec![
+                (image.width.as_ref(), Width),
+                (image.height.as_ref(), Height),
+            ],
+        ),
+        Primitive::Fits(fits) => (
+            Some(&fits.position),
+            vec![(fits.width.as_ref(), Width), (fits.height.as_ref(), Height)],
+        ),
+        Primitive::Icon(icon) => (Some(&icon.position), vec![]),
+        Primitive::Progress(progress) => (
+            Some(&progress.position),
+            vec![
+                (Some(&progress.width), Width),
+                (Some(&progress.height), Height),
+            ],
+        ),
+        Primitive::Chart(chart) => (
+            Some(&chart.position),
+            vec![(Some(&chart.width), Width), (Some(&chart.height), Height)],
+        ),
+        Primitive::Bars(bars) => (
+            Some(&bars.position),
+            vec![(Some(&bars.width), Width), (Some(&bars.height), Height)],
+        ),
+        Primitive::Gauge(gauge) => (
+            Some(&gauge.position),
+            vec![(Some(&gauge.diameter), Smallest)],
+        ),
+        Primitive::Clock(clock) => (
+            Some(&clock.position),
+            vec![(Some(&clock.diameter), Smallest)],
+        ),
+        Primitive::Table(table) => (
+            Some(&table.position),
+            table
+                .columns
+                .iter()
+                .map(|column| (column.width.as_ref(), Width))
+                .collect(),
+        ),
+        Primitive::Container(container) => (Some(&container.position), vec![]),
+    }
+}
+
+fn parts_mut(
+    primitive: &mut Primitive,
+) -> (Option<&mut Point>, Vec<(Option<&mut Length>, Extent)>) {
+    match primitive {
+        Primitive::Dummy(_) => (None, vec![]),
+        Primitive::Text(text) => (
+            Some(&mut text.position),
+            vec![(text.width.as_mut(), Width), (text.height.as_mut(), Height)],
+        ),
+        Primitive::RichText(rich) => (Some(&mut rich.position), vec![(rich.width.as_mut(), Width)]),
+        Primitive::QRCode(qr) => (
+            Some(&mut qr.position),
+            vec![(Some(&mut qr.width), Width), (Some(&mut qr.height), Height)],
+        ),
+        Primitive::Barcode(barcode) => (
+            Some(&mut barcode.position),
+            vec![
+                (Some(&mut barcode.width), Width),
+                (Some(&mut barcode.height), Height),
+            ],
+        ),
+        Primitive::Image(image) => (
+            Some(&mut image.position),
+            vec![
+                (image.width.as_mut(), Width),
+                (image.height.as_mut(), Height),
+            ],
+        ),
+        Primitive::Fits(fits) => (
+            Some(&mut fits.position),
+            vec![(fits.width.as_mut(), Width), (fits.height.as_mut(), Height)],
+        ),
+        Primitive::Icon(icon) => (Some(&mut icon.position), vec![]),
+        Primitive::Progress(progress) => (
+            Some(&mut progress.position),
+            vec![
+                (Some(&mut progress.width), Width),
+                (Some(&mut progress.height), Height),
+            ],
+        ),
+        Primitive::Chart(chart) => (
+            Some(&mut chart.position),
+            vec![
+                (Some(&mut chart.width), Width),
+                (Some(&mut chart.height), Height),
+            ],
+        ),
+        Primitive::Bars(bars) => (
+            Some(&mut bars.position),
+            vec![
+                (Some(&mut bars.width), Width),
+                (Some(&mut bars.height), Height),
+            ],
+        ),
+        Primitive::Gauge(gauge) => (
+            Some(&mut gauge.position),
+            vec![(Some(&mut gauge.diameter), Smallest)],
+        ),
+        Primitive::Clock(clock) => (
+            Some(&mut clock.position),
+            vec![(Some(&mut clock.diameter), Smallest)],
+        ),
+        Primitive::Table(table) => (
+            Some(&mut table.position),
+            table
+                .columns
+                .iter_mut()
+                .map(|column| (column.width.as_mut(), Width))
+                .collect(),
+        ),
+        Primitive::Container(container) => (Some(&mut container.position), vec![]),
+    }
+}
+
+/// The primitive with its position and sizes in pixels, in the bounds of its parent.
+/// It is only copied when some of them are relative
+pub fn resolve_relative<'a>(primitive: &'a Primitive, bounds: &Rectangle) -> Cow<'a, Primitive> {
+    let (position, lengths) = parts(primitive);
+    let relative = position.is_some_and(|position| position.relative.is_some())
+        || lengths
+            .iter()
+            .any(|(length, _)| length.is_some_and(Length::is_relative))
+        || matches!(primitive, Primitive::Container(Container { size: Some(size), .. }) if size.relative.is_some());
+    if !relative {
+        return Cow::Borrowed(primitive);
+    }
+
+    let mut primitive = primitive.clone();
+    if let Primitive::Container(container) = &mut primitive {
+        if let Some(size) = &mut container.size {
+            *size = size.resolve(bounds);
+        }
+    }
+    let (position, lengths) = parts_mut(&mut primitive);
+    if let Some(position) = position {
+        *position = position.resolve(bounds);
+    }
+    for (length, extent) in lengths {
+        if let Some(length) = length {
+            *length = length.resolve(extent.of(bounds));
+        }
+    }
+    Cow::Owned(primitive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::super::tests::*;
+    use super::*;
+    use yaml_merge_keys::serde_yaml;
+
+    #[test]
+    fn test_coordinate_parsing() {
+        let coordinate = |percent, offset| Coordinate {
+            percent: Some(percent),
+            offset,
+        };
+        assert_eq!("12".parse(), Ok(Coordinate::pixels(12)));
+        assert_eq!("-3".parse(), Ok(Coordinate::pixels(-3)));
+        assert_eq!("50%".parse(), Ok(coordinate(50.0, 0)));
+        assert_eq!("50% - 4".parse(), Ok(coordinate(50.0, -4)));
+        assert_eq!("right-8".parse(), Ok(coordinate(100.0, -8)));
+        assert_eq!("bottom".parse(), Ok(coordinate(100.0, 0)));
+        assert_eq!("center+2".parse(), Ok(coordinate(50.0, 2)));
+        assert!("middle-x".parse::<Coordinate>().is_err());
+        assert!("up".parse::<Coordinate>().is_err());
+
+        assert_eq!(coordinate(100.0, -8).resolve(296), 288);
+        assert_eq!(coordinate(33.3, 0).resolve(128), 43);
+        assert_eq!("left+4".parse(), Ok(coordinate(0.0, 4)));
+        assert_eq!("0%".parse::<Coordinate>().unwrap().to_string(), "0%");
+    }
+
+    #[test]
+    fn test_parent_origin() {
+        // Anchors and percentages, 0% included, start at the parent origin, pixels do not
+        let bounds = Rectangle::new(
+            embedded_graphics::prelude::Point::new(10, 20),
+            Size::new(100, 50),
+        );
+        let resolved = |yaml: &str| {
+            let point: Point = serde_yaml::from_str(yaml).unwrap();
+            let point = point.resolve(&bounds);
+            (point.x, point.y)
+        };
+        assert_eq!(resolved("{x: left+4, y: top}"), (14, 20));
+        assert_eq!(resolved("{x: 0%+4, y: 0%}"), (14, 20));
+        assert_eq!(resolved("{x: 1%+4, y: 2%}"), (15, 21));
+        assert_eq!(resolved("{x: 4, y: bottom-1}"), (4, 69));
+    }
+
+    #[test]
+    fn test_yaml() {
+        let point: Point = serde_yaml::from_str("{x: right-8, y: 4}").unwrap();
+        assert_eq!(
+            point,
+            Point {
+                x: -8,
+                y: 4,
+                relative: Some([coordinate(100.0, -8), Coordinate::pixels(4)]),
+            }
+        );
+        assert_eq!(serde_yaml::to_string(&point).unwrap(), "x: 100%-8\ny: 4\n");
+
+        let point: Point = serde_yaml::from_str("{x: 3, y: 4}").unwrap();
+        assert_eq!(point.relative, None);
+        assert_eq!(
+            point.resolve(&Rectangle::new(
+                embedded_graphics::prelude::Point::new(10, 10),
+                Size::new(20, 20)
+            )),
+            point
+        );
+
+        fn coordinate(percent: f64, offset: i32) -> Coordinate {
+            Coordinate {
+                percent: Some(percent),
+                offset,
+            }
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template = parse(
+            serde_yaml::from_str(
+                r#"
+        - container:
+            position: {x: 0, y: 0}
+            size: {width: 100%, height: 50%}
+            border: 1
+        - progress:
+            position: {x: right-1, y: bottom-1}
+            align: right
+            vertical_align: bottom
+            width: 8
+            height: 4
+            value: 1
+            color: "0"
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let small = render(Size::new(24, 12), template.clone(), None);
+        assert_eq!(
+            String::from("\n") + &small,
+            r#"
+ ▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄ 
+ ██████████████████████ 
+ ▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀ 
+███████████████▀▀▀▀▀▀▀▀█
+███████████████        █
+███████████████▄▄▄▄▄▄▄▄█
+"#
+        );
+
+        let large = render(Size::new(40, 16), template, None);
+        assert_eq!(
+            String::from("\n") + &large,
+            r#"
+ ▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄ 
+ ██████████████████████████████████████ 
+ ██████████████████████████████████████ 
+ ▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀ 
+████████████████████████████████████████
+███████████████████████████████▀▀▀▀▀▀▀▀█
+███████████████████████████████        █
+███████████████████████████████▄▄▄▄▄▄▄▄█
+"#
+        );
+    }
+
+    #[test]
+    fn test_relative_lengths() {
+        let template = parse(
+            serde_yaml::from_str(
+                r#"
+        - progress:
+            position: {x: 0, y: 0}
+            width: 50%
+            height: 25% + 2
+            value: 1
+        - gauge:
+            position: {x: 0, y: 0}
+            diameter: 100% - 4
+            value: 1
+        - progress:
+            position: {x: 0, y: 0}
+            width: 8
+            height: 4
+            value: 1
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let bounds = Rectangle::new(embedded_graphics::prelude::Point::zero(), Size::new(40, 20));
+
+        match resolve_relative(&template[0], &bounds) {
+            Cow::Owned(Primitive::Progress(progress)) => {
+                assert_eq!(progress.width, Length::from(20));
+                assert_eq!(progress.height, Length::from(7));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match resolve_relative(&template[1], &bounds) {
+            Cow::Owned(Primitive::Gauge(gauge)) => assert_eq!(gauge.diameter, Length::from(16)),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(
+            resolve_relative(&template[2], &bounds),
+            Cow::Borrowed(_)
+        ));
     }
 }
@@ -1,24 +1,64 @@
 use crate::{error::DrawingError, renderer::positioning::place_rectangle};
 
+use embedded_graphics::primitives::{
+    PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle, StrokeAlignment,
+};
+use embedded_graphics::text::Alignment;
+use embedded_graphics::Drawable;
+
+use super::grid::GridCell;
 use super::{
-    positioning::{Direction, HorizontalAlignment, VerticalAlignment},
+    drawing_error::IntoDrawingError,
+    font::FontChain,
+    inverted::Inverted,
+    positioning::{Direction, HorizontalAlignment, Length, VerticalAlignment},
+    resolve_colors,
+    series::format_value,
+    text::{draw_text, TextItem},
     ColorFromTemplate, Point,
 };
-use embedded_graphics::prelude::{DrawTarget, PixelColor};
+use embedded_graphics::prelude::{DrawTarget, DrawTargetExt, PixelColor, Primitive};
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum LabelPosition {
+    /// Centered on the bar, inverted over the filled part
+    Inside,
+    /// Right of an horizontal bar, below a vertical one
+    Outside,
+}
+
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Progress {
     pub position: Point,
-    pub width: u32,
-    pub height: u32,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub width: Length,
+    pub height: Length,
 
     pub align: Option<HorizontalAlignment>,
     pub vertical_align: Option<VerticalAlignment>,
 
     pub direction: Option<Direction>,
 
+    /// When set, draw a progress bar filled to value / max instead of the stripe pattern
+    pub value: Option<f64>,
+    /// Default to 1, so that value can be given as a fraction
+    pub max: Option<f64>,
+    /// Fill from the right (horizontal) or from the top (vertical)
+    pub reverse: Option<bool>,
+    pub border: Option<bool>,
+    /// Round the ends of the bar
+    pub rounded: Option<bool>,
+    /// Label format. {percent}, {value} and {max} are replaced
+    pub label: Option<String>,
+    pub label_position: Option<LabelPosition>,
+    pub font: Option<String>,
+    pub color: Option<String>,
+    pub background: Option<String>,
+
     /// modulo default to width for direction horizontal and height for direction vertical
     pub modulo: Option<u32>,
 
@@ -29,12 +69,195 @@ pub struct Progress {
     pub base: Option<u32>,
 }
 
+fn format_label(format: &str, value: f64, max: f64, fraction: f64) -> String {
+    format
+        .replace("{percent}", &format!("{:.0}", fraction * 100.0))
+        .replace("{value}", &format_value(value))
+        .replace("{max}", &format_value(max))
+}
+
 pub fn draw_progress<D, TargetColor>(
     display: &mut D,
     progress: &Progress,
 ) -> Result<(), DrawingError>
 where
-    D: DrawTarget<Color = TargetColor, Error: Into<DrawingError>>,
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    match progress.value {
+        Some(value) => draw_bar(display, progress, value),
+        None => draw_pattern(display, progress),
+    }
+}
+
+fn draw_bar<D, TargetColor>(
+    display: &mut D,
+    progress: &Progress,
+    value: f64,
+) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let (back, front) = resolve_colors::<TargetColor>(&progress.color, &progress.background);
+    let max = progress.max.unwrap_or(1.0);
+    let fraction = if max > 0.0 && value.is_finite() {
+        (value / max).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let vertical = matches!(progress.direction, Some(Direction::Vertical));
+
+    let origin = place_rectangle(
+        embedded_graphics::geometry::Size {
+            width: progress.width.pixels(),
+            height: progress.height.pixels(),
+        },
+        progress.align,
+        progress.vertical_align,
+        progress.position.clone(),
+    );
+    let mut bar = Rectangle::new(
+        origin.into(),
+        embedded_graphics::prelude::Size::new(progress.width.pixels(), progress.height.pixels()),
+    );
+
+    // Outside labels take their room from the bar, sized for the full bar label
+    let chain = FontChain::parse(&progress.font);
+    let outside =
+        progress.label.is_some() && matches!(progress.label_position, Some(LabelPosition::Outside));
+    let mut label_box = bar;
+    if outside {
+        if vertical {
+            let room = chain.line_height() + 1;
+            bar.size.height = bar.size.height.saturating_sub(room);
+            label_box.top_left.y += (bar.size.height + 1) as i32;
+            label_box.size.height = chain.line_height();
+        } else {
+            let full = format_label(progress.label.as_ref().unwrap(), max, max, 1.0);
+            let room = chain.line_width(&full) + 2;
+            bar.size.width = bar.size.width.saturating_sub(room);
+            label_box.top_left.x += (bar.size.width + 2) as i32;
+            label_box.size.width = room - 2;
+        }
+    }
+
+    let radius = if progress.rounded.unwrap_or(false) {
+        std::cmp::min(bar.size.width, bar.size.height) / 2
+    } else {
+        0
+    };
+    let shape = |rect: Rectangle, radius: u32| {
+        RoundedRectangle::with_equal_corners(
+            rect,
+            embedded_graphics::prelude::Size::new_equal(radius),
+        )
+    };
+
+    shape(bar, radius)
+        .into_styled(PrimitiveStyle::with_fill(back))
+        .draw(display)
+        .map_err(IntoDrawingError::into_drawing_error)?;
+
+    // The border is separated from the fill by a 1px gap
+    let (inner, inner_radius) = if progress.border.unwrap_or(false) {
+        shape(bar, radius)
+            .into_styled(
+                PrimitiveStyleBuilder::new()
+                    .stroke_color(front)
+                    .stroke_width(1)
+                    .stroke_alignment(StrokeAlignment::Inside)
+                    .build(),
+            )
+            .draw(display)
+            .map_err(IntoDrawingError::into_drawing_error)?;
+        (bar.offset(-2), radius.saturating_sub(2))
+    } else {
+        (bar, radius)
+    };
+
+    // Split the bar along its length into the filled and the empty side
+    let (bar_start, inner_start, inner_length, bar_end) = if vertical {
+        (
+            bar.top_left.y,
+            inner.top_left.y,
+            inner.size.height,
+            bar.top_left.y + bar.size.height as i32,
+        )
+    } else {
+        (
+            bar.top_left.x,
+            inner.top_left.x,
+            inner.size.width,
+            bar.top_left.x + bar.size.width as i32,
+        )
+    };
+    let length = (fraction * inner_length as f64).round() as i32;
+    // Horizontal bars grow to the right and vertical ones upward, unless reversed
+    let from_start = vertical == progress.reverse.unwrap_or(false);
+    let section = |from: i32, to: i32| {
+        let length = (to - from).max(0) as u32;
+        if vertical {
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(bar.top_left.x, from),
+                embedded_graphics::prelude::Size::new(bar.size.width, length),
+            )
+        } else {
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(from, bar.top_left.y),
+                embedded_graphics::prelude::Size::new(length, bar.size.height),
+            )
+        }
+    };
+    let (filled, empty) = if from_start {
+        let cut = inner_start + length;
+        (section(bar_start, cut), section(cut, bar_end))
+    } else {
+        let cut = inner_start + inner_length as i32 - length;
+        (section(cut, bar_end), section(bar_start, cut))
+    };
+
+    if length > 0 {
+        shape(inner, inner_radius)
+            .into_styled(PrimitiveStyle::with_fill(front))
+            .draw(&mut display.clipped(&filled.intersection(&inner)))
+            .map_err(IntoDrawingError::into_drawing_error)?;
+    }
+
+    let Some(format) = &progress.label else {
+        return Ok(());
+    };
+    let label = TextItem {
+        value: format_label(format, value, max, fraction),
+        position: Point {
+            x: label_box.top_left.x,
+            y: label_box.top_left.y,
+            ..Default::default()
+        },
+        font: progress.font.clone(),
+        color: progress.color.clone(),
+        align: Some(if outside && !vertical {
+            Alignment::Left
+        } else {
+            Alignment::Center
+        }),
+        width: Some(label_box.size.width.into()),
+        height: Some(label_box.size.height.into()),
+        wrap: Some(false),
+        vertical_align: Some(VerticalAlignment::Middle),
+        ..Default::default()
+    };
+    if outside {
+        draw_text(display, &label)
+    } else {
+        draw_text(&mut display.clipped(&empty), &label)?;
+        draw_text(&mut Inverted(&mut display.clipped(&filled)), &label)
+    }
+}
+
+fn draw_pattern<D, TargetColor>(display: &mut D, progress: &Progress) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
     TargetColor: PixelColor + ColorFromTemplate,
 {
     let (back, front) = (
@@ -44,8 +267,8 @@ where
 
     let origin = place_rectangle(
         embedded_graphics::geometry::Size {
-            width: progress.width,
-            height: progress.height,
+            width: progress.width.pixels(),
+            height: progress.height.pixels(),
         },
         progress.align,
         progress.vertical_align,
@@ -53,17 +276,17 @@ where
     );
     let mut pixels = Vec::with_capacity(256);
 
-    for y in 0..progress.height {
-        for x in 0..progress.width {
+    for y in 0..progress.height.pixels() {
+        for x in 0..progress.width.pixels() {
             let id = match progress.direction {
-                None | Some(Direction::Horizontal) => x + y * progress.width,
-                Some(Direction::Vertical) => y + x * progress.height,
+                None | Some(Direction::Horizontal) => x + y * progress.width.pixels(),
+                Some(Direction::Vertical) => y + x * progress.height.pixels(),
             };
 
             let v = (progress.base.unwrap_or(0) + id)
                 % progress.modulo.unwrap_or(match progress.direction {
-                    None | Some(Direction::Horizontal) => progress.width,
-                    Some(Direction::Vertical) => progress.height,
+                    None | Some(Direction::Horizontal) => progress.width.pixels(),
+                    Some(Direction::Vertical) => progress.height.pixels(),
                 });
 
             pixels.push(embedded_graphics::Pixel(
@@ -78,13 +301,17 @@ where
                 },
             ));
             if pixels.len() >= 256 {
-                display.draw_iter(pixels).map_err(Into::into)?;
+                display
+                    .draw_iter(pixels)
+                    .map_err(IntoDrawingError::into_drawing_error)?;
                 pixels = Vec::with_capacity(256);
             }
         }
     }
 
-    display.draw_iter(pixels).map_err(Into::into)?;
+    display
+        .draw_iter(pixels)
+        .map_err(IntoDrawingError::into_drawing_error)?;
 
     Ok(())
 }
@@ -106,70 +333,100 @@ mod tests {
             },
             vec![
                 Primitive::Progress(Progress {
-                    position: Point { x: 16, y: 3 },
-                    width: 20,
-                    height: 4,
+                    position: Point {
+                        x: 16,
+                        y: 3,
+                        ..Default::default()
+                    },
+                    width: 20.into(),
+                    height: 4.into(),
                     align: Some(HorizontalAlignment::Center),
                     vertical_align: Some(VerticalAlignment::Middle),
                     direction: Some(Direction::Horizontal),
                     modulo: Some(20),
                     threshold: Some(0),
                     base: None,
+                    ..Default::default()
                 }),
                 Primitive::Progress(Progress {
-                    position: Point { x: 16, y: 8 },
-                    width: 20,
-                    height: 4,
+                    position: Point {
+                        x: 16,
+                        y: 8,
+                        ..Default::default()
+                    },
+                    width: 20.into(),
+                    height: 4.into(),
                     align: Some(HorizontalAlignment::Center),
                     vertical_align: Some(VerticalAlignment::Middle),
                     direction: Some(Direction::Horizontal),
                     modulo: Some(20),
                     threshold: Some(5),
                     base: None,
+                    ..Default::default()
                 }),
                 Primitive::Progress(Progress {
-                    position: Point { x: 16, y: 13 },
-                    width: 20,
-                    height: 4,
+                    position: Point {
+                        x: 16,
+                        y: 13,
+                        ..Default::default()
+                    },
+                    width: 20.into(),
+                    height: 4.into(),
                     align: Some(HorizontalAlignment::Center),
                     vertical_align: Some(VerticalAlignment::Middle),
                     direction: Some(Direction::Horizontal),
                     modulo: Some(20),
                     threshold: Some(15),
                     base: None,
+                    ..Default::default()
                 }),
                 Primitive::Progress(Progress {
-                    position: Point { x: 16, y: 18 },
-                    width: 21,
-                    height: 4,
+                    position: Point {
+                        x: 16,
+                        y: 18,
+                        ..Default::default()
+                    },
+                    width: 21.into(),
+                    height: 4.into(),
                     align: Some(HorizontalAlignment::Center),
                     vertical_align: Some(VerticalAlignment::Middle),
                     direction: Some(Direction::Horizontal),
                     modulo: Some(7),
                     threshold: Some(4),
                     base: Some(0),
+                    ..Default::default()
                 }),
                 Primitive::Progress(Progress {
-                    position: Point { x: 16, y: 23 },
-                    width: 21,
-                    height: 4,
+                    position: Point {
+                        x: 16,
+                        y: 23,
+                        ..Default::default()
+                    },
+                    width: 21.into(),
+                    height: 4.into(),
                     align: Some(HorizontalAlignment::Center),
                     vertical_align: Some(VerticalAlignment::Middle),
                     direction: Some(Direction::Horizontal),
                     modulo: Some(7),
                     threshold: Some(4),
                     base: Some(5),
+                    ..Default::default()
                 }),
                 Primitive::Progress(Progress {
-                    position: Point { x: 16, y: 28 },
-                    width: 21,
-                    height: 4,
+                    position: Point {
+                        x: 16,
+                        y: 28,
+                        ..Default::default()
+                    },
+                    width: 21.into(),
+                    height: 4.into(),
                     align: Some(HorizontalAlignment::Center),
                     vertical_align: Some(VerticalAlignment::Middle),
                     direction: Some(Direction::Horizontal),
                     modulo: Some(7),
                     threshold: Some(4),
                     base: Some(3),
+                    ..Default::default()
                 }),
             ],
             None,
@@ -194,6 +451,116 @@ mod tests {
 ███████   ████   ████   ████████
 ███████   ████   ████   ████████
 ████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_bar_inside_label() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 32,
+                height: 24,
+            },
+            vec![
+                Primitive::Progress(Progress {
+                    position: Point {
+                        x: 0,
+                        y: 0,
+                        ..Default::default()
+                    },
+                    width: 32.into(),
+                    height: 10.into(),
+                    value: Some(3.0),
+                    max: Some(8.0),
+                    border: Some(true),
+                    rounded: Some(true),
+                    label: Some("{percent}%".to_string()),
+                    font: Some("4x6".to_string()),
+                    color: Some("0".to_string()),
+                    ..Default::default()
+                }),
+                Primitive::Progress(Progress {
+                    position: Point {
+                        x: 0,
+                        y: 12,
+                        ..Default::default()
+                    },
+                    width: 32.into(),
+                    height: 10.into(),
+                    value: Some(0.75),
+                    reverse: Some(true),
+                    label: Some("{value}".to_string()),
+                    font: Some("4x6".to_string()),
+                    color: Some("0".to_string()),
+                    ..Default::default()
+                }),
+            ],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+█▀▀▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▀▀█
+▀▄▀       ▀▀██▀▄ █▄█▀█████████▄▀
+ █         ▀▄█▀▄▀█▀▄███████████ 
+▄▀▄       ▀▀ █▄▄████▄█████████▀▄
+█▄▄▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▄▄█
+████████████████████████████████
+████████                        
+████████▄▀▄     ▀▀█ █▀▀         
+█████████▀█     ▄▀  ▀▀▄         
+████████ ▀   ▀  ▀   ▀▀          
+████████                        
+████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_bar_vertical_outside_label() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 24,
+                height: 24,
+            },
+            vec![Primitive::Progress(Progress {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                width: 24.into(),
+                height: 24.into(),
+                direction: Some(Direction::Vertical),
+                value: Some(12.0),
+                max: Some(20.0),
+                border: Some(true),
+                label: Some("{value}/{max}".to_string()),
+                label_position: Some(LabelPosition::Outside),
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+ ▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄ 
+ ██████████████████████ 
+ ██████████████████████ 
+ █▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀█ 
+ █                    █ 
+ █                    █ 
+ █                    █ 
+ █▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄█ 
+▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄
+██▀ ██▀▄▀███ █▀▄▀█▀▄▀███
+███ ███▀▄█▀▄███▀▄█ ▄ ███
+██▄▄▄█▄▄▄█▄███▄▄▄██▄████
 "#
         );
     }
@@ -6,7 +6,9 @@ use crate::{
 use embedded_graphics::pixelcolor::raw::RawU1;
 use embedded_graphics::primitives::Rectangle;
 
-use super::{ColorFromTemplate, Point, Size};
+use super::grid::GridCell;
+use super::positioning::Length;
+use super::{resolve_colors, ColorFromTemplate, Point, Size};
 use embedded_graphics::prelude::*;
 
 use qrcode::render::{Canvas, Pixel};
@@ -66,12 +68,14 @@ mod eclevel {
 pub struct QRCode {
     pub value: String,
     pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
     pub color: Option<String>,
     pub background: Option<String>,
     #[serde(with = "eclevel", default = "eclevel::default")]
     pub ec_level: Option<qrcode::EcLevel>,
-    pub width: u32,
-    pub height: u32,
+    pub width: Length,
+    pub height: Length,
     // #[serde(default = "baseline::default", with = "baseline")]
     // pub baseline: Option<Baseline>,
 }
@@ -174,31 +178,21 @@ where
     let res = render
         .dark_color(DummyPixel(0))
         .light_color(DummyPixel(1))
-        .min_dimensions(qrcode.width, qrcode.height)
-        .max_dimensions(qrcode.width, qrcode.height)
+        .min_dimensions(qrcode.width.pixels(), qrcode.height.pixels())
+        .max_dimensions(qrcode.width.pixels(), qrcode.height.pixels())
         .quiet_zone(true)
         .build();
 
-    let (back, front) = if qrcode.color.is_some() && qrcode.background.is_some() {
-        (
-            TargetColor::resolve(&qrcode.background),
-            TargetColor::resolve(&qrcode.color),
-        )
-    } else if qrcode.background.is_some() {
-        let back = TargetColor::resolve(&qrcode.background);
-        let color = back.invert();
-        (back, color)
-    } else {
-        let color = TargetColor::resolve(&qrcode.color);
-        let back = color.invert();
-        (back, color)
-    };
+    let (back, front) = resolve_colors::<TargetColor>(&qrcode.color, &qrcode.background);
 
     display
         .fill_solid(
             &Rectangle {
                 top_left: qrcode.position.clone().into(),
-                size: embedded_graphics::prelude::Size::new(qrcode.width, qrcode.height),
+                size: embedded_graphics::prelude::Size::new(
+                    qrcode.width.pixels(),
+                    qrcode.height.pixels(),
+                ),
             },
             back,
         )
@@ -207,13 +201,13 @@ where
     let actual_size = res.dimensions();
 
     let mut shift: (i32, i32) = (
-        if actual_size.width < qrcode.width {
-            ((qrcode.width - actual_size.width) / 2) as i32
+        if actual_size.width < qrcode.width.pixels() {
+            ((qrcode.width.pixels() - actual_size.width) / 2) as i32
         } else {
             0
         },
-        if actual_size.height < qrcode.height {
-            ((qrcode.height - actual_size.height) / 2) as i32
+        if actual_size.height < qrcode.height.pixels() {
+            ((qrcode.height.pixels() - actual_size.height) / 2) as i32
         } else {
             0
         },
@@ -257,12 +251,17 @@ mod tests {
             },
             vec![Primitive::QRCode(QRCode {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 0, y: 0 },
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                cell: None,
                 color: Some("1".to_string()),
                 background: None,
                 ec_level: None,
-                width: 32,
-                height: 32,
+                width: 32.into(),
+                height: 32.into(),
             })],
             None,
         );
@@ -299,12 +298,17 @@ mod tests {
             },
             vec![Primitive::QRCode(QRCode {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 0, y: 0 },
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                cell: None,
                 color: Some("0".to_string()),
                 background: None,
                 ec_level: None,
-                width: 32,
-                height: 32,
+                width: 32.into(),
+                height: 32.into(),
             })],
             None,
         );
@@ -341,12 +345,17 @@ mod tests {
             },
             vec![Primitive::QRCode(QRCode {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 16, y: 16 },
+                position: Point {
+                    x: 16,
+                    y: 16,
+                    ..Default::default()
+                },
+                cell: None,
                 color: Some("0".to_string()),
                 background: None,
                 ec_level: None,
-                width: 32,
-                height: 32,
+                width: 32.into(),
+                height: 32.into(),
             })],
             Some(Rectangle::new(
                 embedded_graphics::prelude::Point { x: 0, y: 0 },
@@ -389,12 +398,17 @@ mod tests {
             },
             vec![Primitive::QRCode(QRCode {
                 value: "lés bon amis amös L3s €".to_string(),
-                position: Point { x: 4, y: 6 },
+                position: Point {
+                    x: 4,
+                    y: 6,
+                    ..Default::default()
+                },
+                cell: None,
                 color: Some("1".to_string()),
                 background: None,
                 ec_level: Some(::qrcode::EcLevel::L),
-                width: 82,
-                height: 72,
+                width: 82.into(),
+                height: 72.into(),
             })],
             None,
         );
@@ -0,0 +1,278 @@
+use embedded_graphics::primitives::Rectangle;
+use serde::{Deserialize, Serialize};
+
+use super::positioning::Direction;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Resample {
+    /// Closest source pixel, keeps hard edges
+    Nearest,
+    /// Average of the covered source pixels
+    Area,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Dither {
+    /// Plain threshold
+    None,
+    /// Error diffusion, for photos
+    FloydSteinberg,
+    /// 4x4 Bayer pattern, for flat gradients
+    Ordered,
+}
+
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// Decoded image, as (gray, alpha) pixels, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 2]>,
+}
+
+impl Raster {
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> [u8; 2]) -> Raster {
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Raster {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> [u8; 2] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Part of the raster inside the rectangle
+    pub fn crop(&self, area: &Rectangle) -> Raster {
+        let area = area.intersection(&Rectangle::new(
+            embedded_graphics::prelude::Point::zero(),
+            embedded_graphics::prelude::Size::new(self.width, self.height),
+        ));
+        let (left, top) = (area.top_left.x as u32, area.top_left.y as u32);
+        Raster::from_fn(area.size.width, area.size.height, |x, y| {
+            self.get(left + x, top + y)
+        })
+    }
+
+    /// Mirror the raster left to right (horizontal) or top to bottom (vertical)
+    pub fn flip(&self, direction: Direction) -> Raster {
+        Raster::from_fn(self.width, self.height, |x, y| match direction {
+            Direction::Horizontal => self.get(self.width - 1 - x, y),
+            Direction::Vertical => self.get(x, self.height - 1 - y),
+        })
+    }
+
+    /// Rotate the raster by quarter turns, clockwise
+    pub fn rotate(&self, quarters: i32) -> Raster {
+        let (width, height) = (self.width, self.height);
+        match quarters.rem_euclid(4) {
+            1 => Raster::from_fn(height, width, |x, y| self.get(y, height - 1 - x)),
+            2 => Raster::from_fn(width, height, |x, y| {
+                self.get(width - 1 - x, height - 1 - y)
+            }),
+            3 => Raster::from_fn(height, width, |x, y| self.get(width - 1 - y, x)),
+            _ => self.clone(),
+        }
+    }
+
+    pub fn resize(&self, width: u32, height: u32, resample: Resample) -> Raster {
+        if (width, height) == (self.width, self.height) {
+            return self.clone();
+        }
+        if self.width == 0 || self.height == 0 {
+            return Raster::from_fn(width, height, |_, _| [0, 0]);
+        }
+        let scale_x = self.width as f64 / width as f64;
+        let scale_y = self.height as f64 / height as f64;
+        match resample {
+            Resample::Nearest => Raster::from_fn(width, height, |x, y| {
+                self.get(
+                    (((x as f64 + 0.5) * scale_x) as u32).min(self.width - 1),
+                    (((y as f64 + 0.5) * scale_y) as u32).min(self.height - 1),
+                )
+            }),
+            Resample::Area => Raster::from_fn(width, height, |x, y| {
+                self.average(
+                    (x as f64 * scale_x, (x + 1) as f64 * scale_x),
+                    (y as f64 * scale_y, (y + 1) as f64 * scale_y),
+                )
+            }),
+        }
+    }
+
+    /// Black (false) or white (true) pixels, None where transparent
+    pub fn binarize(&self, threshold: u8, dither: Dither) -> Vec<Option<bool>> {
+        let threshold = threshold as f64;
+        let opaque = |x: u32, y: u32| self.get(x, y)[1] >= 128;
+        match dither {
+            Dither::None => self
+                .pixels
+                .iter()
+                .map(|[gray, alpha]| (*alpha >= 128).then_some(*gray as f64 >= threshold))
+                .collect(),
+            Dither::Ordered => (0..self.height)
+                .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let [gray, alpha] = self.get(x, y);
+                    let bias = (BAYER[y as usize % 4][x as usize % 4] as f64 + 0.5) / 16.0 - 0.5;
+                    (alpha >= 128).then_some(gray as f64 + bias * 255.0 >= threshold)
+                })
+                .collect(),
+            Dither::FloydSteinberg => {
+                let (width, height) = (self.width as usize, self.height as usize);
+                let mut values: Vec<f64> = self.pixels.iter().map(|[g, _]| *g as f64).collect();
+                let mut result = vec![None; values.len()];
+                for y in 0..height {
+                    for x in 0..width {
+                        if !opaque(x as u32, y as u32) {
+                            continue;
+                        }
+                        let value = values[y * width + x];
+                        let white = value >= threshold;
+                        result[y * width + x] = Some(white);
+                        // Transparent pixels neither take nor pass on the error
+                        let error = value - if white { 255.0 } else { 0.0 };
+                        let mut spread = |dx: isize, dy: usize, weight: f64| {
+                            let (x, y) = (x as isize + dx, y + dy);
+                            if x >= 0 && (x as usize) < width && y < height {
+                                values[y * width + x as usize] += error * weight / 16.0;
+                            }
+                        };
+                        spread(1, 0, 7.0);
+                        spread(-1, 1, 3.0);
+                        spread(0, 1, 5.0);
+                        spread(1, 1, 1.0);
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    // Mean of the pixels covered by a source area, weighted by coverage.
+    // Gray is weighted by alpha so that transparent pixels do not bleed in
+    fn average(&self, (x0, x1): (f64, f64), (y0, y1): (f64, f64)) -> [u8; 2] {
+        let (mut gray, mut alpha, mut total) = (0.0, 0.0, 0.0);
+        for y in y0.floor() as u32..(y1.ceil() as u32).min(self.height) {
+            let height = (y1.min(y as f64 + 1.0) - y0.max(y as f64)).max(0.0);
+            for x in x0.floor() as u32..(x1.ceil() as u32).min(self.width) {
+                let width = (x1.min(x as f64 + 1.0) - x0.max(x as f64)).max(0.0);
+                let [g, a] = self.get(x, y);
+                let weight = width * height;
+                gray += g as f64 * a as f64 * weight;
+                alpha += a as f64 * weight;
+                total += weight;
+            }
+        }
+        if alpha <= 0.0 {
+            return [0, 0];
+        }
+        [(gray / alpha).round() as u8, (alpha / total).round() as u8]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raster(width: u32, rows: &[u8]) -> Raster {
+        Raster {
+            width,
+            height: rows.len() as u32 / width,
+            pixels: rows.iter().map(|g| [*g, 255]).collect(),
+        }
+    }
+
+    fn gray(raster: &Raster) -> Vec<u8> {
+        raster.pixels.iter().map(|[g, _]| *g).collect()
+    }
+
+    #[test]
+    fn test_transforms() {
+        // 1 2 3
+        // 4 5 6
+        let source = raster(3, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(gray(&source.rotate(1)), vec![4, 1, 5, 2, 6, 3]);
+        assert_eq!(source.rotate(1).width, 2);
+        assert_eq!(gray(&source.rotate(2)), vec![6, 5, 4, 3, 2, 1]);
+        assert_eq!(gray(&source.rotate(-1)), vec![3, 6, 2, 5, 1, 4]);
+        assert_eq!(
+            gray(&source.flip(Direction::Horizontal)),
+            vec![3, 2, 1, 6, 5, 4]
+        );
+        assert_eq!(
+            gray(&source.flip(Direction::Vertical)),
+            vec![4, 5, 6, 1, 2, 3]
+        );
+        assert_eq!(
+            gray(&source.crop(&Rectangle::new(
+                embedded_graphics::prelude::Point::new(1, 1),
+                embedded_graphics::prelude::Size::new(4, 4)
+            ))),
+            vec![5, 6]
+        );
+    }
+
+    #[test]
+    fn test_resize() {
+        let source = raster(
+            4,
+            &[
+                0, 0, 255, 255, 0, 0, 255, 255, 0, 255, 0, 255, 0, 255, 0, 255,
+            ],
+        );
+        assert_eq!(
+            gray(&source.resize(2, 2, Resample::Area)),
+            vec![0, 255, 128, 128]
+        );
+        assert_eq!(
+            gray(&source.resize(2, 2, Resample::Nearest)),
+            vec![0, 255, 255, 255]
+        );
+        assert_eq!(
+            gray(&raster(2, &[0, 255]).resize(4, 1, Resample::Nearest)),
+            vec![0, 0, 255, 255]
+        );
+
+        // Transparent pixels do not darken their neighbours
+        let icon = Raster {
+            width: 2,
+            height: 1,
+            pixels: vec![[0, 0], [255, 255]],
+        };
+        assert_eq!(icon.resize(1, 1, Resample::Area).pixels, vec![[255, 128]]);
+    }
+
+    #[test]
+    fn test_binarize() {
+        let white = |pixels: Vec<Option<bool>>| pixels.iter().filter(|p| **p == Some(true)).count();
+        let gray = raster(4, &[128; 16]);
+        assert_eq!(white(gray.binarize(128, Dither::None)), 16);
+        assert_eq!(white(gray.binarize(129, Dither::None)), 0);
+        assert_eq!(white(gray.binarize(128, Dither::Ordered)), 8);
+        assert_eq!(white(gray.binarize(128, Dither::FloydSteinberg)), 8);
+
+        // A quarter of the pixels are white, give or take the error left at the edges
+        let dark = raster(8, &[64; 64]);
+        assert_eq!(white(dark.binarize(128, Dither::Ordered)), 16);
+        assert!((15..=16).contains(&white(dark.binarize(128, Dither::FloydSteinberg))));
+
+        let icon = Raster {
+            width: 2,
+            height: 1,
+            pixels: vec![[255, 0], [200, 255]],
+        };
+        assert_eq!(
+            icon.binarize(128, Dither::FloydSteinberg),
+            vec![None, Some(true)]
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use once_cell::sync::Lazy;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::DrawingError;
+
+/// Where the files named by primitives may be read from
+#[derive(Debug, Clone, Default)]
+pub struct Resources {
+    /// Directory of the template, searched first. Default to the working directory
+    pub template_dir: Option<PathBuf>,
+    /// Other directories searched, in order
+    pub roots: Vec<PathBuf>,
+    /// Accept absolute paths and paths going up out of the directories
+    pub allow_any_path: bool,
+}
+
+static RESOURCES: Lazy<Mutex<Resources>> =
+    Lazy::new(|| Mutex::new(Resources::default().canonical()));
+
+pub fn set_resources(resources: Resources) {
+    *RESOURCES.lock().unwrap() = resources.canonical();
+}
+
+/// File named by a primitive, checked against the configured directories
+pub fn resolve(path: &str) -> Result<PathBuf, DrawingError> {
+    RESOURCES.lock().unwrap().resolve(path)
+}
+
+// Path without `.` and `..` components, or None if it goes up above its start
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut result = PathBuf::new();
+    let mut depth = 0;
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if depth == 0 => return None,
+            Component::ParentDir => {
+                result.pop();
+                depth -= 1;
+            }
+            Component::Normal(name) => {
+                result.push(name);
+                depth += 1;
+            }
+            root => result.push(root),
+        }
+    }
+    Some(result)
+}
+
+impl Resources {
+    /// The same resources with absolute directories free of links, so that the paths found in
+    /// them can be checked. The directories which cannot be read are left out
+    pub fn canonical(self) -> Resources {
+        let canonical = |dir: PathBuf| {
+            let dir = match dir.as_os_str().is_empty() {
+                true => PathBuf::from("."),
+                false => dir,
+            };
+            match dir.canonicalize() {
+                Ok(dir) => Some(dir),
+                Err(e) => {
+                    println!("Ignoring resource directory {:?}: {}", dir, e);
+                    None
+                }
+            }
+        };
+        Resources {
+            template_dir: canonical(self.template_dir.unwrap_or_default()),
+            roots: self.roots.into_iter().filter_map(canonical).collect(),
+            allow_any_path: self.allow_any_path,
+        }
+    }
+
+    // Only the canonical directories are searched
+    fn dirs(&self) -> impl Iterator<Item = &PathBuf> {
+        self.template_dir.iter().chain(self.roots.iter())
+    }
+
+    fn inside(&self, path: &Path) -> bool {
+        self.dirs().any(|dir| path.starts_with(dir))
+    }
+
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, DrawingError> {
+        let written = Path::new(path);
+        let forbidden = || DrawingError::ForbiddenResource(path.to_string());
+
+        if written.is_absolute() {
+            let normalized = normalize(written).ok_or_else(forbidden)?;
+            if !self.allow_any_path && !self.inside(&normalized) {
+                return Err(forbidden());
+            }
+            if !normalized.is_file() {
+                return Err(DrawingError::MissingResource(
+                    path.to_string(),
+                    vec![normalized],
+                ));
+            }
+            return match self.allow_any_path {
+                true => Ok(normalized),
+                // Links must not lead out of the directories either
+                false => match normalized.canonicalize() {
+                    Ok(real) if self.inside(&real) => Ok(real),
+                    _ => Err(forbidden()),
+                },
+            };
+        }
+
+        let mut searched = Vec::new();
+        for dir in self.dirs() {
+            if self.allow_any_path {
+                let candidate = dir.join(written);
+                if candidate.is_file() {
+                    return Ok(candidate);
+                }
+                searched.push(candidate);
+                continue;
+            }
+            let Some(candidate) = normalize(&dir.join(written)).filter(|c| c.starts_with(dir))
+            else {
+                continue;
+            };
+            if !candidate.is_file() {
+                searched.push(candidate);
+                continue;
+            }
+            return match candidate.canonicalize() {
+                Ok(real) if real.starts_with(dir) => Ok(real),
+                _ => Err(forbidden()),
+            };
+        }
+        match searched.is_empty() {
+            true => Err(forbidden()),
+            false => Err(DrawingError::MissingResource(path.to_string(), searched)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let normalized = |path: &str| normalize(Path::new(path));
+        assert_eq!(normalized("./a/../b/c"), Some(PathBuf::from("b/c")));
+        assert_eq!(normalized("/a/./b/.."), Some(PathBuf::from("/a")));
+        assert_eq!(normalized("a/../../b"), None);
+        assert_eq!(normalized(""), Some(PathBuf::new()));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let root = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let resources = Resources {
+            template_dir: Some(PathBuf::from("mobindi")),
+            roots: vec![root.join("resources"), root.join("missing")],
+            allow_any_path: false,
+        }
+        .canonical();
+        assert_eq!(resources.roots, vec![root.join("resources")]);
+
+        // Relative to the template first, then to the other roots
+        assert_eq!(
+            resources.resolve("template.yaml").unwrap(),
+            root.join("mobindi/template.yaml")
+        );
+        assert_eq!(
+            resources.resolve("./ethernet.png").unwrap(),
+            root.join("resources/ethernet.png")
+        );
+        assert_eq!(
+            resources
+                .resolve(&root.join("resources/ethernet.png").to_string_lossy())
+                .unwrap(),
+            root.join("resources/ethernet.png")
+        );
+
+        // Going out of the roots
+        for path in ["../Cargo.toml", "/etc/passwd", "../../Cargo.toml"] {
+            assert!(
+                matches!(
+                    resources.resolve(path),
+                    Err(DrawingError::ForbiddenResource(_))
+                ),
+                "{}",
+                path
+            );
+        }
+        assert!(matches!(
+            resources.resolve(&root.join("resources/../Cargo.toml").to_string_lossy()),
+            Err(DrawingError::ForbiddenResource(_))
+        ));
+
+        // Missing files list the places searched
+        match resources.resolve("missing.png") {
+            Err(DrawingError::MissingResource(path, searched)) => {
+                assert_eq!(path, "missing.png");
+                assert_eq!(
+                    searched,
+                    vec![
+                        root.join("mobindi/missing.png"),
+                        root.join("resources/missing.png")
+                    ]
+                );
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let open = Resources {
+            allow_any_path: true,
+            ..resources
+        };
+        assert_eq!(
+            open.resolve("../Cargo.toml").unwrap(),
+            root.join("mobindi/../Cargo.toml")
+        );
+        assert!(open.resolve("/etc/passwd").is_ok());
+
+        // By default, only the working directory is allowed
+        let default = Resources::default().canonical();
+        assert!(default.resolve("resources/ethernet.png").is_ok());
+        assert!(default.resolve("/etc/passwd").is_err());
+        assert!(default
+            .resolve(&root.join("resources/ethernet.png").to_string_lossy())
+            .is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links() {
+        let root = std::env::current_dir().unwrap().canonicalize().unwrap();
+        let dir = std::env::temp_dir().join(format!("resources-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let link = dir.join("escape.toml");
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(root.join("Cargo.toml"), &link).unwrap();
+
+        // A link inside a root must not give access to files out of the roots
+        let resources = Resources {
+            template_dir: Some(root.join("mobindi")),
+            roots: vec![dir.clone()],
+            allow_any_path: false,
+        }
+        .canonical();
+        let escape = resources.resolve("escape.toml");
+        let absolute = resources.resolve(&link.to_string_lossy());
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(escape, Err(DrawingError::ForbiddenResource(_))));
+        assert!(matches!(absolute, Err(DrawingError::ForbiddenResource(_))));
+    }
+}
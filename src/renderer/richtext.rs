@@ -0,0 +1,431 @@
+use crate::error::DrawingError;
+
+use super::drawing_error::IntoDrawingError;
+use super::font::FontChain;
+use super::grid::GridCell;
+use super::icon::{draw_icon, Icon};
+use super::inverted::Inverted;
+use super::positioning::{Length, OptionalLength};
+use super::text::{draw_line, LineStyle};
+use super::{ColorFromTemplate, Point};
+use embedded_graphics::{
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Alignment,
+};
+
+use serde::{Deserialize, Serialize};
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RichText {
+    pub spans: Vec<Span>,
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    /// Default font and color of the spans
+    pub font: Option<String>,
+    pub color: Option<String>,
+    #[serde(with = "super::alignment", default = "super::alignment::default")]
+    pub align: Option<Alignment>,
+    /// When set, position is the top left corner of a box of that width.
+    /// Lines are aligned and wrapped on spaces inside the box
+    pub width: Option<Length>,
+    /// Extra pixels between lines (may be negative)
+    pub line_spacing: Option<i32>,
+}
+
+/// Part of a rich text: some text, or an icon when `icon` is set
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Span {
+    #[serde(default)]
+    pub text: String,
+    /// Name of an icon (see icon::Icon), drawn instead of the text
+    pub icon: Option<String>,
+    /// Size of the icon. Default to the line height of the font
+    pub size: Option<u32>,
+    pub font: Option<String>,
+    pub color: Option<String>,
+    /// Draw the span in the background color over a box of its color
+    pub invert: Option<bool>,
+}
+
+// Smallest unit of the layout: a word, the spaces between words, or an icon
+#[derive(Debug, Clone, PartialEq)]
+struct Piece {
+    span: usize,
+    text: String,
+    space: bool,
+    width: u32,
+    /// Pixels above and below the baseline
+    ascent: u32,
+    descent: u32,
+}
+
+#[derive(Debug, Default)]
+struct Line {
+    pieces: Vec<Piece>,
+    width: u32,
+    ascent: u32,
+    descent: u32,
+}
+
+impl Line {
+    fn push(&mut self, piece: Piece) {
+        self.width += piece.width;
+        self.ascent = self.ascent.max(piece.ascent);
+        self.descent = self.descent.max(piece.descent);
+        self.pieces.push(piece);
+    }
+
+    // Spaces at the end of a wrapped line are not drawn
+    fn trim_end(&mut self) {
+        while self.pieces.last().is_some_and(|piece| piece.space) {
+            let piece = self.pieces.pop().unwrap();
+            self.width -= piece.width;
+        }
+    }
+
+    fn height(&self) -> u32 {
+        self.ascent + self.descent
+    }
+}
+
+fn span_font(rich: &RichText, span: &Span) -> FontChain {
+    FontChain::parse(&span.font.clone().or(rich.font.clone()))
+}
+
+fn icon_size(chain: &FontChain, span: &Span) -> u32 {
+    span.size.unwrap_or(chain.line_height())
+}
+
+// Pieces of all the spans, with None for the line breaks of the texts
+fn pieces(rich: &RichText) -> Vec<Option<Piece>> {
+    let mut pieces = Vec::new();
+    for (index, span) in rich.spans.iter().enumerate() {
+        let chain = span_font(rich, span);
+        let descent = chain.line_height() - chain.baseline();
+        let piece = |text: String, space: bool| Piece {
+            span: index,
+            width: chain.advance(&text),
+            text,
+            space,
+            ascent: chain.baseline(),
+            descent,
+        };
+
+        if span.icon.is_some() {
+            // Icons sit on the bottom of the text line
+            let size = icon_size(&chain, span);
+            pieces.push(Some(Piece {
+                span: index,
+                text: String::new(),
+                space: false,
+                width: size,
+                ascent: size.saturating_sub(descent),
+                descent: descent.min(size),
+            }));
+            continue;
+        }
+
+        for (i, line) in span.text.split('\n').enumerate() {
+            if i > 0 {
+                pieces.push(None);
+            }
+            let mut current = String::new();
+            for c in line.strip_suffix('\r').unwrap_or(line).chars() {
+                if current
+                    .chars()
+                    .last()
+                    .is_some_and(|last| last.is_whitespace() != c.is_whitespace())
+                {
+                    let space = current.starts_with(char::is_whitespace);
+                    pieces.push(Some(piece(std::mem::take(&mut current), space)));
+                }
+                current.push(c);
+            }
+            if !current.is_empty() {
+                let space = current.starts_with(char::is_whitespace);
+                pieces.push(Some(piece(current, space)));
+            }
+        }
+    }
+    pieces
+}
+
+// Split the pieces into lines, wrapping them on spaces when a width is set
+fn layout(rich: &RichText) -> Vec<Line> {
+    let chain = FontChain::parse(&rich.font);
+    let empty = || Line {
+        ascent: chain.baseline(),
+        descent: chain.line_height() - chain.baseline(),
+        ..Default::default()
+    };
+
+    let mut lines = Vec::new();
+    let mut line = empty();
+    let mut wrapped = false;
+    for piece in pieces(rich) {
+        let Some(piece) = piece else {
+            lines.push(std::mem::replace(&mut line, empty()));
+            wrapped = false;
+            continue;
+        };
+        if piece.space && wrapped && line.pieces.is_empty() {
+            continue;
+        }
+        if let Some(width) = rich.width.pixels() {
+            if !piece.space && !line.pieces.is_empty() && line.width + piece.width > width {
+                line.trim_end();
+                lines.push(std::mem::replace(&mut line, empty()));
+                wrapped = true;
+            }
+        }
+        if line.pieces.is_empty() {
+            // The line is as high as its content
+            line.ascent = 0;
+            line.descent = 0;
+        }
+        line.push(piece);
+    }
+    lines.push(line);
+    lines
+}
+
+/// Area covered by the rich text
+pub fn rich_text_bounds(rich: &RichText) -> Rectangle {
+    let lines = layout(rich);
+    let line_spacing = rich.line_spacing.unwrap_or(0);
+    let height = lines
+        .iter()
+        .map(|line| line.height() as i32 + line_spacing)
+        .sum::<i32>()
+        - line_spacing;
+    let width = match rich.width.pixels() {
+        Some(width) => width,
+        None => lines.iter().map(|line| line.width).max().unwrap_or(0),
+    };
+    let x = match rich.width.pixels() {
+        Some(_) => rich.position.x,
+        None => FontChain::align_line(
+            rich.position.x,
+            width,
+            rich.align.unwrap_or(Alignment::Left),
+        ),
+    };
+    Rectangle::new(
+        embedded_graphics::prelude::Point::new(x, rich.position.y),
+        embedded_graphics::prelude::Size::new(width, height.max(0) as u32),
+    )
+}
+
+// Text or icon of the piece, in the color of its span
+fn draw_content<D, TargetColor>(
+    display: &mut D,
+    rich: &RichText,
+    piece: &Piece,
+    x: i32,
+    baseline: i32,
+) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let span = &rich.spans[piece.span];
+    let color = span.color.clone().or(rich.color.clone());
+    match &span.icon {
+        Some(name) => draw_icon(
+            display,
+            &Icon {
+                name: name.clone(),
+                position: Point {
+                    x,
+                    y: baseline + piece.descent as i32 - piece.width as i32,
+                    relative: None,
+                },
+                size: Some(piece.width),
+                color,
+                ..Default::default()
+            },
+        ),
+        None => {
+            let chain = span_font(rich, span);
+            draw_line(
+                display,
+                &chain,
+                &piece.text,
+                embedded_graphics::prelude::Point::new(x, baseline - chain.baseline() as i32),
+                &LineStyle::new(TargetColor::resolve(&color)),
+            )
+        }
+    }
+}
+
+fn draw_piece<D, TargetColor>(
+    display: &mut D,
+    rich: &RichText,
+    piece: &Piece,
+    x: i32,
+    baseline: i32,
+) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let span = &rich.spans[piece.span];
+    if !span.invert.unwrap_or(false) {
+        return draw_content(display, rich, piece, x, baseline);
+    }
+    let color = span.color.clone().or(rich.color.clone());
+    Rectangle::new(
+        embedded_graphics::prelude::Point::new(x, baseline - piece.ascent as i32),
+        embedded_graphics::prelude::Size::new(piece.width, piece.ascent + piece.descent),
+    )
+    .into_styled(PrimitiveStyle::with_fill(TargetColor::resolve(&color)))
+    .draw(display)
+    .map_err(IntoDrawingError::into_drawing_error)?;
+    draw_content(&mut Inverted(display), rich, piece, x, baseline)
+}
+
+fn draw_lines<D, TargetColor>(display: &mut D, rich: &RichText) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let align = rich.align.unwrap_or(Alignment::Left);
+    let mut y = rich.position.y;
+    for line in layout(rich) {
+        let mut x = match rich.width.pixels() {
+            None => FontChain::align_line(rich.position.x, line.width, align),
+            Some(width) => {
+                rich.position.x
+                    + match align {
+                        Alignment::Left => 0,
+                        Alignment::Center => (width as i32 - line.width as i32) / 2,
+                        Alignment::Right => width as i32 - line.width as i32,
+                    }
+            }
+        };
+        let baseline = y + line.ascent as i32;
+        for piece in &line.pieces {
+            draw_piece(display, rich, piece, x, baseline)?;
+            x += piece.width as i32;
+        }
+        y += line.height() as i32 + rich.line_spacing.unwrap_or(0);
+    }
+    Ok(())
+}
+
+pub fn draw_rich_text<D, TargetColor>(display: &mut D, rich: &RichText) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    match rich.width.pixels() {
+        None => draw_lines(display, rich),
+        // Words longer than the box are clipped
+        Some(width) => {
+            let bounds = display.bounding_box();
+            let clip = Rectangle::new(
+                rich.position.clone().into(),
+                embedded_graphics::prelude::Size::new(width, bounds.size.height),
+            );
+            draw_lines(&mut display.clipped(&clip), rich)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse;
+    use super::super::tests::*;
+    use super::*;
+    use yaml_merge_keys::serde_yaml;
+
+    #[test]
+    fn test_layout() {
+        let span = |text: &str| Span {
+            text: text.to_string(),
+            ..Default::default()
+        };
+        let rich = RichText {
+            spans: vec![
+                span("Guiding "),
+                Span {
+                    font: Some("6x13_bold".to_string()),
+                    ..span("lost")
+                },
+                span(" on star\nRA 5.2\""),
+            ],
+            font: Some("4x6".to_string()),
+            width: Some(60.into()),
+            ..Default::default()
+        };
+        let lines = layout(&rich);
+        let texts: Vec<String> = lines
+            .iter()
+            .map(|line| line.pieces.iter().map(|p| p.text.clone()).collect())
+            .collect();
+        assert_eq!(texts, vec!["Guiding lost", "on star", "RA 5.2\""]);
+        // The bold word raises the first line
+        assert_eq!(
+            lines
+                .iter()
+                .map(|line| (line.width, line.ascent, line.descent))
+                .collect::<Vec<_>>(),
+            vec![(56, 10, 3), (28, 4, 2), (28, 4, 2)]
+        );
+        assert_eq!(
+            rich_text_bounds(&rich),
+            Rectangle::new(
+                embedded_graphics::prelude::Point::zero(),
+                embedded_graphics::prelude::Size::new(60, 25)
+            )
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let template = parse(
+            serde_yaml::from_str(
+                r#"
+        - richtext:
+            position: { x: 1, y: 1 }
+            font: 4x6
+            color: black
+            spans:
+              - icon: warning
+                size: 8
+                invert: true
+              - text: " LOST "
+                invert: true
+              - text: " Guiding "
+              - text: "12:34"
+                font: 6x10
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 64,
+                height: 10,
+            },
+            template,
+            None,
+        );
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████████████████████████████████████████████████████████████████
+█   ▄▄   ███████████████████████████████████████████████████████
+█  ▄██▄      █   ▄▀▄ ▄▀▀ ▀█▀     ████▀▄▄█▀█▀██▄███▀ ██▄██▀▀███▀▀
+█ ▄████▄     █   █ █  ▀▄  █      ████ █ █ █ █▄ ██ █ █▄ ██ █ █▄▀ 
+█ ▀▀▀▀▀▀     ▀▀▀  ▀  ▀▀   ▀      █████▄▄██▄▄█▄▄▄██▄▄█▄▄▄█▄█▄█▀▀▄
+"#
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use serde_json::Value;
+
+use crate::state;
+
+/// Numeric values, given inline or read from an array in the state.
+/// Non numeric entries are kept as NaN so that gaps are visible
+pub fn resolve_series(values: &Option<Vec<f64>>, path: &Option<String>) -> Vec<f64> {
+    if let Some(values) = values {
+        return values.clone();
+    }
+
+    match path.as_ref().and_then(|p| state::get_path(p)) {
+        Some(Value::Array(vec)) => vec.iter().map(|v| v.as_f64().unwrap_or(f64::NAN)).collect(),
+        Some(v) => {
+            println!("Not an array at {:?}: {:?}", path, v);
+            vec![]
+        }
+        None => vec![],
+    }
+}
+
+/// (min, max) of the finite values, overridden by the fixed bounds when given.
+/// Never returns an empty range
+pub fn bounds(values: &[f64], min: Option<f64>, max: Option<f64>) -> (f64, f64) {
+    let finite = values.iter().copied().filter(|v| v.is_finite());
+    let lo = min.unwrap_or_else(|| finite.clone().fold(f64::INFINITY, f64::min));
+    let hi = max.unwrap_or_else(|| finite.fold(f64::NEG_INFINITY, f64::max));
+
+    match (lo.is_finite(), hi.is_finite()) {
+        (false, false) => (0.0, 1.0),
+        (true, false) => (lo, lo + 1.0),
+        (false, true) => (hi - 1.0, hi),
+        _ if lo >= hi => (lo - 0.5, lo + 0.5),
+        _ => (lo, hi),
+    }
+}
+
+/// Short representation for labels: at most 2 decimals, no trailing zeros
+pub fn format_value(v: f64) -> String {
+    let s = format!("{:.2}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(bounds(&[1.0, f64::NAN, 3.0], None, None), (1.0, 3.0));
+        assert_eq!(bounds(&[1.0, 3.0], Some(0.0), None), (0.0, 3.0));
+        assert_eq!(bounds(&[2.0, 2.0], None, None), (1.5, 2.5));
+        assert_eq!(bounds(&[], None, None), (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(1.0), "1");
+        assert_eq!(format_value(0.25), "0.25");
+        assert_eq!(format_value(-1.5), "-1.5");
+        assert_eq!(format_value(2.0 / 3.0), "0.67");
+        assert_eq!(format_value(-0.001), "0");
+    }
+}
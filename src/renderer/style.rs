@@ -0,0 +1,202 @@
+use embedded_graphics::text::Alignment;
+use serde::{Deserialize, Serialize};
+
+use super::Primitive;
+
+/// Defaults inherited by the primitives of a container, and by nested containers
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Style {
+    pub font: Option<String>,
+    pub color: Option<String>,
+    pub background: Option<String>,
+    /// Alignment of texts and table columns
+    #[serde(with = "super::alignment", default = "super::alignment::default")]
+    pub align: Option<Alignment>,
+    pub line_spacing: Option<i32>,
+}
+
+fn inherit<T: Clone>(field: &mut Option<T>, default: &Option<T>) {
+    if field.is_none() {
+        field.clone_from(default);
+    }
+}
+
+impl Style {
+    /// This style, completed by the parent one
+    pub fn over(&self, parent: &Style) -> Style {
+        let mut style = self.clone();
+        inherit(&mut style.font, &parent.font);
+        inherit(&mut style.color, &parent.color);
+        inherit(&mut style.background, &parent.background);
+        inherit(&mut style.align, &parent.align);
+        inherit(&mut style.line_spacing, &parent.line_spacing);
+        style
+    }
+
+    /// Whether applying the style could change the primitive
+    pub fn affects(&self, primitive: &Primitive) -> bool {
+        !matches!(
+            primitive,
+            Primitive::Dummy(_) | Primitive::Image(_) | Primitive::Fits(_)
+        ) && *self != Style::default()
+    }
+
+    /// Fill the unset settings of the primitive
+    pub fn apply(&self, primitive: &mut Primitive) {
+        match primitive {
+            Primitive::Dummy(_) | Primitive::Image(_) | Primitive::Fits(_) => {}
+            Primitive::Text(text) => {
+                inherit(&mut text.font, &self.font);
+                inherit(&mut text.color, &self.color);
+                inherit(&mut text.background, &self.background);
+                inherit(&mut text.align, &self.align);
+                inherit(&mut text.line_spacing, &self.line_spacing);
+            }
+            // Rich texts have no background: only their inverted spans fill boxes
+            Primitive::RichText(rich) => {
+                inherit(&mut rich.font, &self.font);
+                inherit(&mut rich.color, &self.color);
+                inherit(&mut rich.align, &self.align);
+                inherit(&mut rich.line_spacing, &self.line_spacing);
+            }
+            Primitive::QRCode(qr) => {
+                inherit(&mut qr.color, &self.color);
+                inherit(&mut qr.background, &self.background);
+            }
+            Primitive::Barcode(barcode) => {
+                inherit(&mut barcode.color, &self.color);
+                inherit(&mut barcode.background, &self.background);
+            }
+            Primitive::Progress(progress) => {
+                inherit(&mut progress.font, &self.font);
+                inherit(&mut progress.color, &self.color);
+                inherit(&mut progress.background, &self.background);
+            }
+            Primitive::Chart(chart) => {
+                inherit(&mut chart.font, &self.font);
+                inherit(&mut chart.color, &self.color);
+            }
+            Primitive::Bars(bars) => {
+                inherit(&mut bars.font, &self.font);
+                inherit(&mut bars.color, &self.color);
+            }
+            Primitive::Gauge(gauge) => {
+                inherit(&mut gauge.font, &self.font);
+                inherit(&mut gauge.color, &self.color);
+                for zone in gauge.zones.iter_mut().flatten() {
+                    inherit(&mut zone.color, &self.color);
+                }
+            }
+            Primitive::Clock(clock) => inherit(&mut clock.color, &self.color),
+            Primitive::Icon(icon) => inherit(&mut icon.color, &self.color),
+            Primitive::Table(table) => {
+                inherit(&mut table.color, &self.color);
+                for column in table.columns.iter_mut() {
+                    inherit(&mut column.font, &self.font);
+                    inherit(&mut column.align, &self.align);
+                }
+            }
+            // The container passes the style on to its content, so that it is measured with it
+            Primitive::Container(container) => {
+                inherit(&mut container.border_color, &self.color);
+                container.style = Some(container.style.take().unwrap_or_default().over(self));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::{parse, Primitive};
+    use super::*;
+    use yaml_merge_keys::serde_yaml;
+
+    #[test]
+    fn test_over() {
+        let parent = Style {
+            font: Some("6x10".to_string()),
+            color: Some("1".to_string()),
+            align: Some(Alignment::Center),
+            ..Default::default()
+        };
+        let child = Style {
+            color: Some("0".to_string()),
+            line_spacing: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            child.over(&parent),
+            Style {
+                font: Some("6x10".to_string()),
+                color: Some("0".to_string()),
+                background: None,
+                align: Some(Alignment::Center),
+                line_spacing: Some(2),
+            }
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let template = parse(
+            serde_yaml::from_str(
+                r#"
+        style:
+          font: 4x6
+          color: "0"
+        content:
+          - text:
+              value: "Root"
+              position: {x: 0, y: 0}
+          - container:
+              position: {x: 0, y: 6}
+              size: {width: 32, height: 12}
+              style:
+                align: center
+                color: "1"
+                background: "0"
+              content:
+                - !text
+                  value: "Mid"
+                  position: {x: 16, y: 0}
+                - !text
+                  value: "Own"
+                  position: {x: 16, y: 6}
+                  font: 5x7
+                - !progress
+                  position: {x: 0, y: 0}
+                  width: 6
+                  height: 12
+                  value: 0.5
+                  border: true
+                  color: "1"
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(matches!(template[..], [Primitive::Container(_)]));
+
+        let display = render(
+            embedded_graphics::prelude::Size::new(32, 18),
+            template,
+            None,
+        );
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+ ▄▀██▀███▀██▀ ▀█████████████████
+ ▄▀█ █ █ █ ██ ██████████████████
+▄█▄██▄███▄████▄█████████████████
+█▀▀▀▀███████▄█  ▀   ▄█ █████████
+█ █  ███████▀█ ▀█  █ █ █████████
+█ █  ██████▀ ▀ ▀▀▀  ▀▀ █████████
+█ █  ████▄▀▀▄           ████████
+█ █  █████  █ █  █ █▀▀▄ ████████
+█▄▄▄▄████▀▄▄▀ ████ █  █ ████████
+"#
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use resvg::{tiny_skia, usvg};
+
+use super::decode::gray;
+use super::raster::Raster;
+
+/// Bytes looked at to recognize an SVG document
+const SNIFF_LENGTH: usize = 1024;
+
+/// Whether the bytes hold an SVG document rather than a bitmap. The root element is expected
+/// near the start, after the XML declaration and a few comments
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(SNIFF_LENGTH)]);
+    text.trim_start_matches('\u{feff}')
+        .trim_start()
+        .starts_with('<')
+        && text.contains("<svg")
+}
+
+/// Texts are not rendered, as no fonts are loaded. Embedded images are drawn, but the ones
+/// referring to files are left out, as they would not be checked against the resource directories
+pub fn parse(bytes: &[u8]) -> Result<usvg::Tree, usvg::Error> {
+    let options = usvg::Options {
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_string: Box::new(|href, _| {
+                println!("Ignoring SVG image {:?}", href);
+                None
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    usvg::Tree::from_data(bytes, &options)
+}
+
+/// Size of the document, in its own pixels
+pub fn size(tree: &usvg::Tree) -> (f64, f64) {
+    (tree.size().width() as f64, tree.size().height() as f64)
+}
+
+/// Render the (x, y, width, height) area of the document, scaled by the given factors
+pub fn rasterize(
+    tree: &usvg::Tree,
+    (x, y, width, height): (f64, f64, f64, f64),
+    (scale_x, scale_y): (f64, f64),
+) -> Raster {
+    let pixels_width = (width * scale_x).round() as u32;
+    let pixels_height = (height * scale_y).round() as u32;
+    let Some(mut pixmap) = tiny_skia::Pixmap::new(pixels_width, pixels_height) else {
+        return Raster::from_fn(pixels_width, pixels_height, |_, _| [0, 0]);
+    };
+    let transform = tiny_skia::Transform::from_translate(-x as f32, -y as f32)
+        .post_scale(scale_x as f32, scale_y as f32);
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    Raster {
+        width: pixels_width,
+        height: pixels_height,
+        pixels: pixmap
+            .pixels()
+            .iter()
+            .map(|pixel| {
+                let color = pixel.demultiply();
+                [
+                    gray(color.red(), color.green(), color.blue()),
+                    color.alpha(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rasterize() {
+        let svg = br#"<?xml version="1.0"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+  <rect x="0" y="0" width="2" height="2" fill="white"/>
+  <rect x="2" y="0" width="2" height="2" fill="black"/>
+</svg>"#;
+        assert!(is_svg(svg));
+        assert!(!is_svg(b"P1 1 1 0"));
+        let mut late = b"<?xml version=\"1.0\"?>".to_vec();
+        late.resize(SNIFF_LENGTH, b' ');
+        late.extend_from_slice(b"<svg/>");
+        assert!(!is_svg(&late));
+
+        let tree = parse(svg).unwrap();
+        assert_eq!(size(&tree), (4.0, 2.0));
+        let raster = rasterize(&tree, (0.0, 0.0, 4.0, 2.0), (0.5, 0.5));
+        assert_eq!(raster.pixels, vec![[255, 255], [0, 255]]);
+        let raster = rasterize(&tree, (1.0, 0.0, 2.0, 1.0), (1.0, 1.0));
+        assert_eq!(raster.pixels, vec![[255, 255], [0, 255]]);
+    }
+
+    #[test]
+    fn test_file_images() {
+        let png = std::env::current_dir()
+            .unwrap()
+            .join("resources/ethernet.png");
+        assert!(png.is_file());
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="2">
+  <image href="{}" width="4" height="2"/>
+</svg>"#,
+            png.display()
+        );
+        let tree = parse(svg.as_bytes()).unwrap();
+        assert!(tree.root().children().is_empty());
+    }
+}
@@ -0,0 +1,371 @@
+use crate::error::DrawingError;
+use crate::state;
+
+use embedded_graphics::primitives::Rectangle;
+use embedded_graphics::text::Alignment;
+use serde_json::Value;
+
+use super::drawing_error::IntoDrawingError;
+use super::font::FontChain;
+use super::grid::GridCell;
+use super::positioning::{Length, OptionalLength, VerticalAlignment};
+use super::text::{draw_text, TextItem};
+use super::{ColorFromTemplate, Point};
+use embedded_graphics::prelude::{DrawTarget, PixelColor};
+use serde::{Deserialize, Serialize};
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Column {
+    /// Header of the column. The header row is drawn when any column has a title
+    pub title: Option<String>,
+    /// Field of the row when rows are objects. Default to the column index in array rows
+    pub key: Option<String>,
+    /// Default to the widest cell of the column
+    pub width: Option<Length>,
+    #[serde(with = "super::alignment", default = "super::alignment::default")]
+    pub align: Option<Alignment>,
+    pub font: Option<String>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Table {
+    pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
+    pub columns: Vec<Column>,
+
+    /// Rows, as arrays of cells or objects keyed by column key
+    pub rows: Option<Vec<Value>>,
+    /// Dotted path to an array of rows in the state (used when rows is not set)
+    pub path: Option<String>,
+    pub max_rows: Option<u32>,
+
+    /// Default to the font of the first column
+    pub header_font: Option<String>,
+    /// Default to the tallest column font
+    pub row_height: Option<u32>,
+    /// Pixels between columns. Default to 2
+    pub column_spacing: Option<u32>,
+    /// Draw a line between rows. The header is always underlined
+    pub separators: Option<bool>,
+
+    pub color: Option<String>,
+}
+
+fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(v) => v.to_string(),
+    }
+}
+
+fn resolve_rows(table: &Table) -> Vec<Value> {
+    let rows = match (&table.rows, &table.path) {
+        (Some(rows), _) => rows.clone(),
+        (None, Some(path)) => match state::get_path(path) {
+            Some(Value::Array(rows)) => rows,
+            Some(v) => {
+                println!("Not an array at {:?}: {:?}", path, v);
+                vec![]
+            }
+            None => vec![],
+        },
+        (None, None) => vec![],
+    };
+    match table.max_rows {
+        Some(max) => rows.into_iter().take(max as usize).collect(),
+        None => rows,
+    }
+}
+
+/// Text of each cell, row by row
+fn cells(table: &Table, rows: &[Value]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| {
+            table
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| match (row, &column.key) {
+                    (Value::Object(o), Some(key)) => cell_text(o.get(key)),
+                    (Value::Array(a), _) => cell_text(a.get(i)),
+                    (v, _) if i == 0 => cell_text(Some(v)),
+                    _ => String::new(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Sizes of the table, shared by drawing and measuring
+struct TableLayout {
+    rows: Vec<Vec<String>>,
+    header_font: Option<String>,
+    header_height: Option<u32>,
+    widths: Vec<u32>,
+    spacing: u32,
+    row_height: u32,
+    separators: bool,
+}
+
+impl TableLayout {
+    fn new(table: &Table) -> TableLayout {
+        let rows = cells(table, &resolve_rows(table));
+        let header_font = table
+            .header_font
+            .clone()
+            .or_else(|| table.columns.first().and_then(|c| c.font.clone()));
+        let header_chain = FontChain::parse(&header_font);
+        let chains: Vec<FontChain> = table
+            .columns
+            .iter()
+            .map(|c| FontChain::parse(&c.font))
+            .collect();
+        let has_header = table.columns.iter().any(|c| c.title.is_some());
+
+        let widths = table
+            .columns
+            .iter()
+            .zip(chains.iter())
+            .enumerate()
+            .map(|(i, (column, chain))| {
+                column.width.pixels().unwrap_or_else(|| {
+                    let header = if has_header {
+                        header_chain.line_width(column.title.as_deref().unwrap_or(""))
+                    } else {
+                        0
+                    };
+                    rows.iter()
+                        .map(|row| chain.line_width(&row[i]))
+                        .fold(header, u32::max)
+                })
+            })
+            .collect();
+        let row_height = table
+            .row_height
+            .unwrap_or_else(|| chains.iter().map(|c| c.line_height()).max().unwrap_or(0));
+
+        TableLayout {
+            rows,
+            header_font,
+            header_height: has_header.then(|| header_chain.line_height()),
+            widths,
+            spacing: table.column_spacing.unwrap_or(2),
+            row_height,
+            separators: table.separators.unwrap_or(false),
+        }
+    }
+
+    fn width(&self) -> u32 {
+        self.widths.iter().sum::<u32>()
+            + self.spacing * (self.widths.len() as u32).saturating_sub(1)
+    }
+
+    // Rules are surrounded by a 1px gap
+    fn height(&self) -> u32 {
+        let rows = self.rows.len() as u32;
+        let rules = if self.separators {
+            rows.saturating_sub(1)
+        } else {
+            0
+        };
+        self.header_height.map(|h| h + 3).unwrap_or(0) + rows * self.row_height + rules * 3
+    }
+}
+
+/// Area covered by the table
+pub fn table_bounds(table: &Table) -> Rectangle {
+    let layout = TableLayout::new(table);
+    Rectangle::new(
+        table.position.clone().into(),
+        embedded_graphics::prelude::Size::new(layout.width(), layout.height()),
+    )
+}
+
+pub fn draw_table<D, TargetColor>(display: &mut D, table: &Table) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let color = TargetColor::resolve(&table.color);
+    let layout = TableLayout::new(table);
+    let total_width = layout.width();
+    let TableLayout {
+        rows,
+        header_font,
+        header_height,
+        widths,
+        spacing,
+        row_height,
+        separators,
+    } = layout;
+
+    let mut y = table.position.y;
+    let rule = |display: &mut D, y: i32| {
+        display
+            .fill_solid(
+                &Rectangle::new(
+                    embedded_graphics::prelude::Point::new(table.position.x, y),
+                    embedded_graphics::prelude::Size::new(total_width, 1),
+                ),
+                color,
+            )
+            .map_err(IntoDrawingError::into_drawing_error)
+    };
+
+    let draw_row = |display: &mut D, cells: Vec<(&str, &Option<String>)>, y: i32, height: u32| {
+        let mut x = table.position.x;
+        for ((text, font), (column, width)) in cells
+            .into_iter()
+            .zip(table.columns.iter().zip(widths.iter()))
+        {
+            draw_text(
+                display,
+                &TextItem {
+                    value: text.to_string(),
+                    position: Point {
+                        x,
+                        y,
+                        ..Default::default()
+                    },
+                    font: font.clone(),
+                    color: table.color.clone(),
+                    align: column.align,
+                    width: Some((*width).into()),
+                    height: Some(height.into()),
+                    wrap: Some(false),
+                    vertical_align: Some(VerticalAlignment::Middle),
+                    ..Default::default()
+                },
+            )?;
+            x += (width + spacing) as i32;
+        }
+        Ok::<(), DrawingError>(())
+    };
+
+    if let Some(height) = header_height {
+        let cells = table
+            .columns
+            .iter()
+            .map(|c| (c.title.as_deref().unwrap_or(""), &header_font))
+            .collect();
+        draw_row(display, cells, y, height)?;
+        rule(display, y + height as i32 + 1)?;
+        y += height as i32 + 3;
+    }
+
+    for (i, row) in rows.iter().enumerate() {
+        if separators && i > 0 {
+            rule(display, y + 1)?;
+            y += 3;
+        }
+        let cells = row
+            .iter()
+            .zip(table.columns.iter())
+            .map(|(text, column)| (text.as_str(), &column.font))
+            .collect();
+        draw_row(display, cells, y, row_height)?;
+        y += row_height as i32;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::super::Primitive;
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cells() {
+        let table = Table {
+            columns: vec![
+                Column {
+                    key: Some("filter".to_string()),
+                    ..Default::default()
+                },
+                Column {
+                    key: Some("count".to_string()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            cells(
+                &table,
+                &[
+                    json!({"filter": "Ha", "count": 12}),
+                    json!(["OIII", 3.5, "ignored"]),
+                    json!({"filter": "SII"}),
+                    json!("L"),
+                ]
+            ),
+            vec![
+                vec!["Ha".to_string(), "12".to_string()],
+                vec!["OIII".to_string(), "3.5".to_string()],
+                vec!["SII".to_string(), "".to_string()],
+                vec!["L".to_string(), "".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 48,
+                height: 24,
+            },
+            vec![Primitive::Table(Table {
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                columns: vec![
+                    Column {
+                        title: Some("F".to_string()),
+                        font: Some("4x6".to_string()),
+                        ..Default::default()
+                    },
+                    Column {
+                        title: Some("Exp".to_string()),
+                        width: Some(20.into()),
+                        align: Some(Alignment::Right),
+                        font: Some("4x6".to_string()),
+                        ..Default::default()
+                    },
+                ],
+                rows: Some(vec![json!(["Ha", 300]), json!(["OIII", 60])]),
+                separators: Some(true),
+                color: Some("0".to_string()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+ ▄▄███████████████████████ ▄▄█▀█▀█▀▀████████████
+ ▄████████████████████████ ▄███ ██ ▀▄███████████
+▄█████████████████████████▄▄▄█▄█▄█ █████████████
+▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀▀██████████
+▀█▀███████████████████████▀▀▀██▀███▀████████████
+ ▀ █▀▄ ████████████████████▀▄█ ▀ █ ▀ ███████████
+ █ █▄▀ ███████████████████▀▀▄█▄▀▄█▄▀▄███████████
+████████████████████████████████████████████████
+▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄██████████
+▀▄▀█▄ ▄█▄ ▄█▄ ▄███████████████▀▄▄█▀▄▀███████████
+ █ ██ ███ ███ ████████████████ ▄▀█ ▄ ███████████
+█▄██▄▄▄█▄▄▄█▄▄▄████████████████▄███▄████████████
+"#
+        );
+    }
+}
@@ -1,78 +1,502 @@
 use crate::error::DrawingError;
 
 use super::drawing_error::IntoDrawingError;
-use super::{ColorFromTemplate, Point};
-use embedded_graphics::{mono_font::MonoFont, text::Alignment, Drawable};
+use super::font::{FontChain, FONT_NAMES};
+use super::grid::GridCell;
+use super::positioning::{Length, OptionalLength, VerticalAlignment};
+use super::{resolve_colors, ColorFromTemplate, Point};
 use embedded_graphics::{
     mono_font::MonoTextStyleBuilder,
     prelude::*,
+    primitives::Rectangle,
     text::{Baseline, Text, TextStyleBuilder},
 };
+use embedded_graphics::{text::Alignment, Drawable};
 
 use serde::{Deserialize, Serialize};
 
 #[serde_with::skip_serializing_none]
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct TextItem {
     pub value: String,
     pub position: Point,
+    /// Cell in a grid parent
+    pub cell: Option<GridCell>,
     pub font: Option<String>,
     pub color: Option<String>,
     #[serde(with = "super::alignment", default = "super::alignment::default")]
     pub align: Option<Alignment>,
-    // #[serde(default = "baseline::default", with = "baseline")]
-    // pub baseline: Option<Baseline>,
-}
-
-pub fn resolve_font(font: &Option<String>) -> &MonoFont<'static> {
-    match font {
-        None => &embedded_graphics::mono_font::ascii::FONT_6X10,
-        Some(font) => match font.to_ascii_uppercase().as_str() {
-            "4X6" => &embedded_graphics::mono_font::ascii::FONT_4X6,
-            "5X7" => &embedded_graphics::mono_font::ascii::FONT_5X7,
-            "5X8" => &embedded_graphics::mono_font::ascii::FONT_5X8,
-            "6X9" => &embedded_graphics::mono_font::ascii::FONT_6X9,
-            "6X10" => &embedded_graphics::mono_font::ascii::FONT_6X10,
-            "6X12" => &embedded_graphics::mono_font::ascii::FONT_6X12,
-            "6X13" => &embedded_graphics::mono_font::ascii::FONT_6X13,
-            "6X13_BOLD" => &embedded_graphics::mono_font::ascii::FONT_6X13_BOLD,
-            "6X13_ITALIC" => &embedded_graphics::mono_font::ascii::FONT_6X13_ITALIC,
-            "7X13" => &embedded_graphics::mono_font::ascii::FONT_7X13,
-            "7X13_BOLD" => &embedded_graphics::mono_font::ascii::FONT_7X13_BOLD,
-            "7X13_ITALIC" => &embedded_graphics::mono_font::ascii::FONT_7X13_ITALIC,
-            "7X14" => &embedded_graphics::mono_font::ascii::FONT_7X14,
-            "7X14_BOLD" => &embedded_graphics::mono_font::ascii::FONT_7X14_BOLD,
-            "8X13" => &embedded_graphics::mono_font::ascii::FONT_8X13,
-            "8X13_BOLD" => &embedded_graphics::mono_font::ascii::FONT_8X13_BOLD,
-            "8X13_ITALIC" => &embedded_graphics::mono_font::ascii::FONT_8X13_ITALIC,
-            "9X15" => &embedded_graphics::mono_font::ascii::FONT_9X15,
-            "9X15_BOLD" => &embedded_graphics::mono_font::ascii::FONT_9X15_BOLD,
-            "9X18" => &embedded_graphics::mono_font::ascii::FONT_9X18,
-            "9X18_BOLD" => &embedded_graphics::mono_font::ascii::FONT_9X18_BOLD,
-            "10X20" => &embedded_graphics::mono_font::ascii::FONT_10X20,
-            _ => &embedded_graphics::mono_font::ascii::FONT_6X10,
-        },
+    /// Vertical meaning of position when there is no box. Default to top
+    #[serde(default = "super::baseline::default", with = "super::baseline")]
+    pub baseline: Option<Baseline>,
+    /// When set, position is the top left corner of a box of that width.
+    /// Text is aligned, wrapped and clipped inside the box
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    pub max_lines: Option<u32>,
+    /// Wrap on words (default). When false, lines that overflow the width are ellipsized
+    pub wrap: Option<bool>,
+    /// Vertical position of the text inside the box (requires height)
+    pub vertical_align: Option<VerticalAlignment>,
+    /// Extra pixels between lines (may be negative)
+    pub line_spacing: Option<i32>,
+    /// Use the largest of these fonts for which the text fits the box (overrides font)
+    pub fit: Option<FontFit>,
+    /// Color of the character cells; alone, the text takes the opposite color
+    pub background: Option<String>,
+    /// Swap the text and background colors
+    pub invert: Option<bool>,
+    /// Surround the glyphs with a 1 pixel line of the opposite color
+    pub outline: Option<bool>,
+    pub underline: Option<bool>,
+    pub strikethrough: Option<bool>,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum FontFit {
+    /// Candidate font specifications (see font::resolve_font)
+    Fonts(Vec<String>),
+    /// Regular built-in fonts whose height is in the range
+    Range {
+        min_height: Option<u32>,
+        max_height: Option<u32>,
+    },
+}
+
+impl FontFit {
+    fn candidates(&self) -> Vec<FontChain> {
+        let specs: Vec<String> = match self {
+            FontFit::Fonts(fonts) => fonts.clone(),
+            FontFit::Range {
+                min_height,
+                max_height,
+            } => FONT_NAMES
+                .iter()
+                .filter(|name| !name.ends_with("_BOLD") && !name.ends_with("_ITALIC"))
+                .map(|name| name.to_string())
+                .filter(|name| {
+                    let height = FontChain::parse(&Some(name.clone())).line_height();
+                    height >= min_height.unwrap_or(0) && height <= max_height.unwrap_or(u32::MAX)
+                })
+                .collect(),
+        };
+
+        let mut chains: Vec<FontChain> = specs
+            .into_iter()
+            .map(|spec| FontChain::parse(&Some(spec)))
+            .collect();
+        // Largest first
+        chains.sort_by_key(|chain| {
+            let size = chain.primary().character_size;
+            std::cmp::Reverse(size.width * size.height)
+        });
+        chains
     }
 }
 
-pub fn draw_text<D, TargetColor>(display: &mut D, text: &TextItem) -> Result<(), DrawingError>
+// Size of the text wrapped as it will be drawn, before it is cut to the height. Words that
+// have to be split count with their full width, so that a smaller font is preferred
+fn natural_size(chain: &FontChain, text: &TextItem) -> embedded_graphics::prelude::Size {
+    let wrap_width = text.width.pixels().filter(|_| text.wrap.unwrap_or(true));
+    let mut width = 0;
+    let mut lines = 0;
+    for paragraph in text.value.split('\n') {
+        let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+        let Some(wrap_width) = wrap_width else {
+            width = width.max(chain.line_width(paragraph));
+            lines += 1;
+            continue;
+        };
+        for line in wrap_paragraph(chain, paragraph, wrap_width) {
+            width = width.max(chain.line_width(&line));
+            lines += 1;
+        }
+        for word in paragraph.split_whitespace() {
+            width = width.max(chain.line_width(word));
+        }
+    }
+    let height = lines * (chain.line_height() as i32 + text.line_spacing.unwrap_or(0))
+        - text.line_spacing.unwrap_or(0);
+    embedded_graphics::prelude::Size::new(width, std::cmp::max(height, 0) as u32)
+}
+
+/// Font chain used for the text, taking auto fit into account
+pub fn choose_font(text: &TextItem) -> FontChain {
+    let candidates = match &text.fit {
+        None => vec![],
+        Some(fit) => fit.candidates(),
+    };
+    if candidates.is_empty() {
+        return FontChain::parse(&text.font);
+    }
+
+    for chain in &candidates {
+        let size = natural_size(chain, text);
+        if size.width <= text.width.pixels().unwrap_or(u32::MAX)
+            && size.height <= text.height.pixels().unwrap_or(u32::MAX)
+        {
+            return chain.clone();
+        }
+    }
+    // Nothing fits: use the smallest
+    candidates.last().unwrap().clone()
+}
+
+const ELLIPSIS: &str = "...";
+
+// Greedy word wrapping of a single paragraph. Words larger than width are split.
+fn wrap_paragraph(chain: &FontChain, paragraph: &str, width: u32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in paragraph.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if chain.line_width(&candidate) <= width {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            current.push(c);
+            if current.chars().count() > 1 && chain.line_width(&current) > width {
+                current.pop();
+                lines.push(std::mem::take(&mut current));
+                current.push(c);
+            }
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+// Shorten the line until it fits in width with the ellipsis appended
+fn ellipsize(chain: &FontChain, line: &str, width: Option<u32>) -> String {
+    let mut line = line.trim_end().to_string();
+    if let Some(width) = width {
+        while !line.is_empty() && chain.line_width(&(line.clone() + ELLIPSIS)) > width {
+            line.pop();
+            line = line.trim_end().to_string();
+        }
+    }
+    line + ELLIPSIS
+}
+
+/// Split the text into the lines that will actually be drawn
+pub fn layout_lines(chain: &FontChain, text: &TextItem) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.value.split('\n') {
+        let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+        match text.width.pixels() {
+            Some(width) if text.wrap.unwrap_or(true) => {
+                lines.extend(wrap_paragraph(chain, paragraph, width))
+            }
+            Some(width) if chain.line_width(paragraph) > width => {
+                lines.push(ellipsize(chain, paragraph, Some(width)))
+            }
+            _ => lines.push(paragraph.to_string()),
+        }
+    }
+
+    let mut max_lines = text.max_lines.map(|m| m as usize);
+    if let Some(height) = text.height.pixels() {
+        let spacing = text.line_spacing.unwrap_or(0);
+        let per_line = chain.line_height() as i32 + spacing;
+        if per_line > 0 {
+            let fit = std::cmp::max(1, (height as i32 + spacing) / per_line) as usize;
+            max_lines = Some(max_lines.map_or(fit, |m| std::cmp::min(m, fit)));
+        }
+    }
+
+    if let Some(max_lines) = max_lines {
+        if lines.len() > max_lines {
+            lines.truncate(max_lines);
+            if let Some(last) = lines.pop() {
+                lines.push(ellipsize(chain, &last, text.width.pixels()));
+            }
+        }
+    }
+    lines
+}
+
+/// Colors and decorations of the glyphs of a line
+#[derive(Debug, Clone, Copy)]
+pub struct LineStyle<C> {
+    pub color: C,
+    pub background: Option<C>,
+    pub underline: bool,
+    pub strikethrough: bool,
+}
+
+impl<C> LineStyle<C> {
+    pub fn new(color: C) -> LineStyle<C> {
+        LineStyle {
+            color,
+            background: None,
+            underline: false,
+            strikethrough: false,
+        }
+    }
+}
+
+/// Draw a single line with its top left corner at position
+pub fn draw_line<D, TargetColor>(
+    display: &mut D,
+    chain: &FontChain,
+    line: &str,
+    position: embedded_graphics::prelude::Point,
+    line_style: &LineStyle<TargetColor>,
+) -> Result<(), DrawingError>
 where
     D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
-    TargetColor: PixelColor + ColorFromTemplate,
+    TargetColor: PixelColor,
 {
-    let style = MonoTextStyleBuilder::new()
-        .font(resolve_font(&text.font))
-        .text_color(TargetColor::resolve(&text.color))
-        .build();
     let text_style = TextStyleBuilder::new()
-        .baseline(Baseline::Top)
-        .alignment(text.align.unwrap_or(Alignment::Left))
+        .baseline(Baseline::Alphabetic)
+        .alignment(Alignment::Left)
         .build();
 
-    Text::with_text_style(&text.value, text.position.clone().into(), style, text_style)
+    let mut x = position.x;
+    let y = position.y + chain.baseline() as i32;
+    // Each run is drawn with the first font of the chain that has its glyphs
+    for (font, run) in chain.runs(line) {
+        let mut style = MonoTextStyleBuilder::new()
+            .font(font)
+            .text_color(line_style.color);
+        if let Some(background) = line_style.background {
+            style = style.background_color(background);
+        }
+        if line_style.underline {
+            style = style.underline();
+        }
+        if line_style.strikethrough {
+            style = style.strikethrough();
+        }
+        let style = style.build();
+        Text::with_text_style(
+            run,
+            embedded_graphics::prelude::Point::new(x, y),
+            style,
+            text_style,
+        )
         .draw(display)
-        .map(|_| ())
-        .map_err(Into::into)
+        .map_err(IntoDrawingError::into_drawing_error)?;
+        x += (run.chars().count() as u32 * (font.character_size.width + font.character_spacing))
+            as i32;
+    }
+    Ok(())
+}
+
+const OUTLINE: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+// Shift from position to the top of the first line, following the baseline setting
+fn baseline_offset(chain: &FontChain, text: &TextItem) -> i32 {
+    if text.width.pixels().is_some() || text.height.pixels().is_some() {
+        return 0;
+    }
+    match text.baseline.unwrap_or(Baseline::Top) {
+        Baseline::Top => 0,
+        Baseline::Middle => -(chain.line_height() as i32 - 1) / 2,
+        Baseline::Bottom => -(chain.line_height() as i32 - 1),
+        Baseline::Alphabetic => -(chain.baseline() as i32),
+    }
+}
+
+fn draw_lines<D, TargetColor>(
+    display: &mut D,
+    chain: &FontChain,
+    text: &TextItem,
+    lines: &[String],
+) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    // Like other primitives, a background alone gives the inverse color
+    let (back, color) = resolve_colors::<TargetColor>(&text.color, &text.background);
+    let background = text.background.as_ref().map(|_| back);
+    let style = match text.invert.unwrap_or(false) {
+        false => LineStyle {
+            color,
+            background,
+            underline: text.underline.unwrap_or(false),
+            strikethrough: text.strikethrough.unwrap_or(false),
+        },
+        true => LineStyle {
+            color: background.unwrap_or(color.invert()),
+            background: Some(color),
+            underline: text.underline.unwrap_or(false),
+            strikethrough: text.strikethrough.unwrap_or(false),
+        },
+    };
+    // With an outline, the character cells are filled first, then the outline is drawn shifted
+    // around the glyphs, and the glyphs last, so that each layer keeps the previous one visible
+    let outline = text.outline.unwrap_or(false);
+    let cells = style.background.map(|background| LineStyle {
+        background: Some(background),
+        ..LineStyle::new(background)
+    });
+    let halo = LineStyle {
+        color: style.color.invert(),
+        background: None,
+        ..style
+    };
+    let glyphs = match outline {
+        true => LineStyle {
+            background: None,
+            ..style
+        },
+        false => style,
+    };
+    let align = text.align.unwrap_or(Alignment::Left);
+    let line_step = chain.line_height() as i32 + text.line_spacing.unwrap_or(0);
+
+    let mut y = text.position.y + baseline_offset(chain, text);
+    if let Some(height) = text.height.pixels() {
+        let block_height = lines.len() as i32 * line_step - text.line_spacing.unwrap_or(0);
+        y += match text.vertical_align {
+            None | Some(VerticalAlignment::Top) => 0,
+            Some(VerticalAlignment::Middle) => (height as i32 - block_height) / 2,
+            Some(VerticalAlignment::Bottom) => height as i32 - block_height,
+        };
+    }
+
+    for line in lines {
+        let line_width = chain.line_width(line);
+        let x = match text.width.pixels() {
+            None => FontChain::align_line(text.position.x, line_width, align),
+            Some(width) => {
+                text.position.x
+                    + match align {
+                        Alignment::Left => 0,
+                        Alignment::Center => (width as i32 - line_width as i32) / 2,
+                        Alignment::Right => width as i32 - line_width as i32,
+                    }
+            }
+        };
+        if outline {
+            if let Some(cells) = &cells {
+                draw_line(
+                    display,
+                    chain,
+                    line,
+                    embedded_graphics::prelude::Point::new(x, y),
+                    cells,
+                )?;
+            }
+            for (dx, dy) in OUTLINE {
+                draw_line(
+                    display,
+                    chain,
+                    line,
+                    embedded_graphics::prelude::Point::new(x + dx, y + dy),
+                    &halo,
+                )?;
+            }
+        }
+        draw_line(
+            display,
+            chain,
+            line,
+            embedded_graphics::prelude::Point::new(x, y),
+            &glyphs,
+        )?;
+        y += line_step;
+    }
+    Ok(())
+}
+
+/// Area covered by the text
+pub fn text_bounds(text: &TextItem) -> Rectangle {
+    let chain = choose_font(text);
+    let lines = layout_lines(&chain, text);
+    let line_spacing = text.line_spacing.unwrap_or(0);
+    let width = lines
+        .iter()
+        .map(|line| chain.line_width(line))
+        .max()
+        .unwrap_or(0);
+    let height = lines.len() as i32 * (chain.line_height() as i32 + line_spacing) - line_spacing;
+    let natural = embedded_graphics::prelude::Size::new(width, height.max(0) as u32);
+
+    if text.width.pixels().is_none() && text.height.pixels().is_none() {
+        let x = FontChain::align_line(
+            text.position.x,
+            natural.width,
+            text.align.unwrap_or(Alignment::Left),
+        );
+        let area = Rectangle::new(
+            embedded_graphics::prelude::Point::new(
+                x,
+                text.position.y + baseline_offset(&chain, text),
+            ),
+            natural,
+        );
+        return match text.outline.unwrap_or(false) {
+            true => area.offset(1),
+            false => area,
+        };
+    }
+    // Without a width, lines are still aligned around the position
+    let x = match text.width.pixels() {
+        Some(_) => text.position.x,
+        None => FontChain::align_line(
+            text.position.x,
+            natural.width,
+            text.align.unwrap_or(Alignment::Left),
+        ),
+    };
+    Rectangle::new(
+        embedded_graphics::prelude::Point::new(x, text.position.y),
+        embedded_graphics::prelude::Size::new(
+            text.width.pixels().unwrap_or(natural.width),
+            text.height.pixels().unwrap_or(natural.height),
+        ),
+    )
+}
+
+pub fn draw_text<D, TargetColor>(display: &mut D, text: &TextItem) -> Result<(), DrawingError>
+where
+    D: DrawTarget<Color = TargetColor, Error: IntoDrawingError>,
+    TargetColor: PixelColor + ColorFromTemplate,
+{
+    let chain = choose_font(text);
+    let lines = layout_lines(&chain, text);
+
+    if text.width.pixels().is_none() && text.height.pixels().is_none() {
+        return draw_lines(display, &chain, text, &lines);
+    }
+
+    // Clip to the box, only along the set dimensions
+    let bounds = display.bounding_box();
+    let (x, width) = match text.width.pixels() {
+        Some(width) => (text.position.x, width),
+        None => (bounds.top_left.x, bounds.size.width),
+    };
+    let (y, height) = match text.height.pixels() {
+        Some(height) => (text.position.y, height),
+        None => (bounds.top_left.y, bounds.size.height),
+    };
+    let clip = Rectangle::new(
+        embedded_graphics::prelude::Point::new(x, y),
+        embedded_graphics::prelude::Size::new(width, height),
+    );
+    draw_lines(&mut display.clipped(&clip), &chain, text, &lines)
 }
 
 #[cfg(test)]
@@ -91,10 +515,15 @@ mod tests {
             },
             vec![Primitive::Text(TextItem {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 0, y: 0 },
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
                 font: None,
                 color: Some("0".to_string()),
                 align: None,
+                ..Default::default()
             })],
             Some(Rectangle::new(
                 embedded_graphics::prelude::Point { x: 0, y: 0 },
@@ -127,10 +556,15 @@ mod tests {
             },
             vec![Primitive::Text(TextItem {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 0, y: 0 },
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
                 font: Some("4x6".to_string()),
                 color: Some("0".to_string()),
                 align: None,
+                ..Default::default()
             })],
             Some(Rectangle::new(
                 embedded_graphics::prelude::Point { x: 0, y: 0 },
@@ -163,10 +597,15 @@ mod tests {
             },
             vec![Primitive::Text(TextItem {
                 value: "Hello, World!".to_string(),
-                position: Point { x: 40, y: 0 },
+                position: Point {
+                    x: 40,
+                    y: 0,
+                    ..Default::default()
+                },
                 font: Some("4x6".to_string()),
                 color: Some("0".to_string()),
                 align: Some(Alignment::Center),
+                ..Default::default()
             })],
             Some(Rectangle::new(
                 embedded_graphics::prelude::Point { x: 0, y: 0 },
@@ -186,6 +625,380 @@ mod tests {
 █████████████████████████████████████████████████████████████████████████████████
 █████████████████████████████████████████████████████████████████████████████████
 █████████████████████████████████████████████████████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_latin1() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 40,
+                height: 8,
+            },
+            vec![Primitive::Text(TextItem {
+                value: "-5°C µ".to_string(),
+                position: Point {
+                    x: 0,
+                    y: 0,
+                    ..Default::default()
+                },
+                font: Some("ascii:4x6,4x6".to_string()),
+                color: Some("0".to_string()),
+                align: None,
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+████ ▄▄█▀▄▀█▀▄▀█████▀█▀█████████████████
+▄▄▄█▄▄▀██▄██ █▀█████ █ █████████████████
+████▄▄███████▄██████ ▄██████████████████
+████████████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_layout_lines() {
+        let chain = FontChain::parse(&Some("4x6".to_string()));
+        let text = TextItem {
+            value: "M31 Andromeda galaxy\nexposure_0001.fits".to_string(),
+            width: Some(40.into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            layout_lines(&chain, &text),
+            vec!["M31", "Andromeda", "galaxy", "exposure_0", "001.fits"]
+        );
+
+        let text = TextItem {
+            max_lines: Some(2),
+            ..text
+        };
+        assert_eq!(layout_lines(&chain, &text), vec!["M31", "Androme..."]);
+
+        let text = TextItem {
+            wrap: Some(false),
+            max_lines: None,
+            ..text
+        };
+        assert_eq!(
+            layout_lines(&chain, &text),
+            vec!["M31 And...", "exposur..."]
+        );
+    }
+
+    #[test]
+    fn test_render_box() {
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 40,
+                height: 16,
+            },
+            vec![Primitive::Text(TextItem {
+                value: "Guiding lost on star".to_string(),
+                position: Point {
+                    x: 2,
+                    y: 1,
+                    ..Default::default()
+                },
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                align: Some(Alignment::Center),
+                width: Some(36.into()),
+                height: Some(14.into()),
+                vertical_align: Some(VerticalAlignment::Middle),
+                line_spacing: Some(1),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+███████▀▀██████▀████▀██▀████████████████
+██████ █▀█ █ █▀▀██▀▄ █▀▀██ ▄▀█▀▄ ███████
+██████▄▀ █▄▀ █▀ ▀█▄▀ █▀ ▀█ █ ██▄ ███████
+██████████████████████████████▄▄████████
+██▄ ███▀███▀▀█▀ ▀██████▀████████████████
+███ ██ █ █▄▄▀██ ██████ █ ███████████████
+██▄▄▄██▄██▄▄████▄██████▄███▄███▄███▄████
+████████████████████████████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_height_only() {
+        // Right aligned lines extend left of the position, they must not be clipped
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 40,
+                height: 8,
+            },
+            vec![Primitive::Text(TextItem {
+                value: "Right".to_string(),
+                position: Point {
+                    x: 30,
+                    y: 1,
+                    ..Default::default()
+                },
+                font: Some("4x6".to_string()),
+                color: Some("0".to_string()),
+                align: Some(Alignment::Right),
+                height: Some(6.into()),
+                ..Default::default()
+            })],
+            None,
+        );
+
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+███████████▀▀███▀██████▀████▀███████████
+███████████ ▀▄█▀▀██▀▄ █ ▄▀█▄ ▄██████████
+███████████ █ █▀ ▀██▄ █ █ ██▄▀██████████
+███████████████████▄▄███████████████████
+"#
+        );
+    }
+
+    #[test]
+    fn test_fit() {
+        let text: TextItem = serde_yaml::from_str(
+            r#"
+            value: "1:23:45"
+            position: { x: 0, y: 0 }
+            width: 64
+            height: 20
+            fit: [ "6x10", "10x20", "9x18_bold" ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(choose_font(&text).line_height(), 18);
+
+        let text = TextItem {
+            value: "12:34".to_string(),
+            ..text
+        };
+        assert_eq!(choose_font(&text).line_height(), 20);
+
+        let text = TextItem {
+            value: "01:23:45.678".to_string(),
+            ..text
+        };
+        assert_eq!(choose_font(&text).line_height(), 10);
+
+        let text: TextItem = serde_yaml::from_str(
+            r#"
+            value: "1:23:45"
+            position: { x: 0, y: 0 }
+            width: 60
+            fit: { min_height: 8, max_height: 14 }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            text.fit,
+            Some(FontFit::Range {
+                min_height: Some(8),
+                max_height: Some(14)
+            })
+        );
+        assert_eq!(choose_font(&text).primary().character_size.width, 8);
+
+        // Wrapped on words, the larger font fits
+        let text: TextItem = serde_yaml::from_str(
+            r#"
+            value: "Dome open"
+            position: { x: 0, y: 0 }
+            width: 50
+            height: 40
+            fit: [ "6x10", "10x20" ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(choose_font(&text).line_height(), 20);
+        let text = TextItem {
+            wrap: Some(false),
+            ..text
+        };
+        assert_eq!(choose_font(&text).line_height(), 10);
+    }
+
+    #[test]
+    fn test_baseline() {
+        let text: TextItem = serde_yaml::from_str(
+            r#"
+            value: "Ag"
+            position: { x: 10, y: 20 }
+            font: 6x10
+            baseline: alphabetic
+            outline: true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(text.baseline, Some(Baseline::Alphabetic));
+        assert_eq!(
+            text_bounds(&text),
+            Rectangle::new(
+                embedded_graphics::prelude::Point::new(9, 12),
+                embedded_graphics::prelude::Size::new(14, 12)
+            )
+        );
+
+        let text = TextItem {
+            baseline: Some(Baseline::Bottom),
+            outline: None,
+            ..text
+        };
+        assert_eq!(
+            text_bounds(&text).top_left,
+            embedded_graphics::prelude::Point::new(10, 11)
+        );
+    }
+
+    #[test]
+    fn test_decorations() {
+        let template = super::super::parse(
+            serde_yaml::from_str(
+                r#"
+        - container:
+            position: {x: 24, y: 0}
+            size: {width: 24, height: 16}
+            background: "0"
+        - text:
+            value: "Dec"
+            position: {x: 26, y: 1}
+            font: 6x10
+            color: "0"
+            outline: true
+        - text:
+            value: "RA"
+            position: {x: 1, y: 1}
+            font: 4x6
+            color: "0"
+            invert: true
+            underline: true
+        - text:
+            value: "Alt"
+            position: {x: 1, y: 15}
+            font: 4x6
+            color: "0"
+            baseline: alphabetic
+            strikethrough: true
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 48,
+                height: 16,
+            },
+            template,
+            None,
+        );
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+███▀▀▀█▀▀███████████████ ▄▄▄▄▄▄                 
+██▄▀ █▄█ ███████████████ █▄ ▄▄▀█▄▄▄▄▄ ▄▄▄▄▄     
+██ █ █ █ ███████████████  █ ██ █▀▄▄▄▀█▀▄▄▄▀█    
+█▄▄▄▄▄▄▄▄███████████████ ▄█ ██ █ ▄▄▄▄█ █▄█▀█    
+████████████████████████ █▄▄▄▄█▀█▄▄▄█▀█▄▄▄█▀    
+██▀██▀▀███▀█████████████                        
+█ ▀ ██ ██▄ ▄████████████                        
+█ ▄ ▄   ▄▄▄ ▄███████████                        
+"#
+        );
+    }
+
+    #[test]
+    fn test_outline_background() {
+        // The outline stays visible over the background of the cells, and the glyphs over it
+        let template = super::super::parse(
+            serde_yaml::from_str(
+                r#"
+        - text:
+            value: "Dec"
+            position: {x: 1, y: 1}
+            font: 4x6
+            color: "0"
+            background: "0"
+            outline: true
+        - text:
+            value: "RA"
+            position: {x: 18, y: 1}
+            font: 4x6
+            color: "1"
+            background: "1"
+            invert: true
+            outline: true
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 28,
+                height: 8,
+            },
+            template,
+            None,
+        );
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+█▀▀█████▀████████ ▄▄ ▀ ▄ ▀██
+█ █ █▀▄▀█▀▄▄█████ █▄▀ █▄█ ██
+█ ▀▄█▄ ▀█▄▀▀█████ █ █ █ █ ██
+████▄████████████▄▄▄▄▄▄▄▄▄██
+"#
+        );
+    }
+
+    #[test]
+    fn test_background_alone() {
+        // Like other primitives, a background alone gives glyphs of the opposite color
+        let template = super::super::parse(
+            serde_yaml::from_str(
+                r#"
+        - text:
+            value: "On"
+            position: {x: 1, y: 1}
+            font: 4x6
+            background: "0"
+        "#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        let display = render(
+            embedded_graphics::prelude::Size {
+                width: 12,
+                height: 8,
+            },
+            template,
+            None,
+        );
+        assert_eq!(
+            String::from("\n") + &display,
+            r#"
+█▀█▀▀▀▀▀▀███
+██ █ █▀▄ ███
+█▀▄▀ █ █ ███
+█▄▄▄▄▄▄▄▄███
 "#
         );
     }
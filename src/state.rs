@@ -158,6 +158,24 @@ pub fn get_state() -> Arc<Value> {
     STATE.lock().unwrap().root.clone()
 }
 
+// Walk a dotted path (e.g. "guiding.rms" or "filters.0.name") in value
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for key in path.split('.').filter(|k| !k.is_empty()) {
+        current = match current {
+            Value::Object(map) => map.get(key)?,
+            Value::Array(vec) => vec.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Value at the given dotted path in the current state
+pub fn get_path(path: &str) -> Option<Value> {
+    lookup(&get_state(), path).cloned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,6 +217,22 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_lookup() {
+        let value = json!({
+            "guiding": {
+                "rms": [0.5, 0.7],
+            },
+            "filters": [{ "name": "L" }, { "name": "Ha" }],
+        });
+
+        assert_eq!(lookup(&value, "guiding.rms"), Some(&json!([0.5, 0.7])));
+        assert_eq!(lookup(&value, "filters.1.name"), Some(&json!("Ha")));
+        assert_eq!(lookup(&value, "filters.2.name"), None);
+        assert_eq!(lookup(&value, "guiding.rms.x"), None);
+        assert_eq!(lookup(&value, ""), Some(&value));
+    }
+
     #[test]
     fn test_cleanup() {
         let value = json!({